@@ -0,0 +1,66 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="forest" tilewidth="32" tileheight="32" tilecount="16" columns="8">
+ <image source="../../tilesets/terrain.png" width="256" height="64"/>
+ <tile id="0" type="grass">
+  <properties>
+   <property name="char" value="."/>
+   <property name="color" value="darkgreen"/>
+   <property name="walkable" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="1" type="wall">
+  <properties>
+   <property name="char" value="#"/>
+   <property name="color" value="gray"/>
+   <property name="walkable" type="bool" value="false"/>
+  </properties>
+ </tile>
+ <tile id="2" type="water">
+  <properties>
+   <property name="char" value="~"/>
+   <property name="color" value="blue"/>
+   <property name="walkable" type="bool" value="false"/>
+  </properties>
+  <animation>
+   <frame tileid="2" duration="300"/>
+   <frame tileid="8" duration="300"/>
+   <frame tileid="9" duration="300"/>
+   <frame tileid="8" duration="300"/>
+  </animation>
+ </tile>
+ <tile id="3" type="tree">
+  <properties>
+   <property name="char" value="T"/>
+   <property name="color" value="green"/>
+   <property name="walkable" type="bool" value="false"/>
+  </properties>
+ </tile>
+ <tile id="4" type="stone">
+  <properties>
+   <property name="char" value="S"/>
+   <property name="color" value="lightgray"/>
+   <property name="walkable" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="5" type="path">
+  <properties>
+   <property name="char" value="P"/>
+   <property name="color" value="brown"/>
+   <property name="walkable" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="6" type="flower">
+  <properties>
+   <property name="char" value="F"/>
+   <property name="color" value="pink"/>
+   <property name="walkable" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="7" type="bush">
+  <properties>
+   <property name="char" value="B"/>
+   <property name="color" value="green"/>
+   <property name="walkable" type="bool" value="false"/>
+  </properties>
+ </tile>
+</tileset>
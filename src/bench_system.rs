@@ -0,0 +1,112 @@
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::time::Instant;
+use crate::color_system::parse_color;
+use crate::sprite_system::{Facing, SpriteData, SpriteRenderer, load_sprites};
+
+const MONSTER_COUNTS: [usize; 6] = [10, 50, 100, 250, 500, 1000];
+// Frames drawn before measuring, then measured, for each count and method
+const WARMUP_FRAMES: usize = 10;
+const MEASURED_FRAMES: usize = 60;
+const SPRITE_SCALE: f32 = 2.0;
+
+pub fn bench_requested() -> bool {
+    std::env::args().any(|arg| arg == "--bench-sprites")
+}
+
+#[derive(Clone, Copy)]
+enum Method {
+    PerPixel,
+    Atlas,
+}
+
+// Draws crowds of outlined monsters the old per-pixel way and from the baked
+// atlas, and prints how long each frame takes. "draw" is the time spent
+// issuing the draw calls; "frame" includes handing the batch to the GPU and
+// is capped from below by vsync.
+pub async fn run_sprite_benchmark() {
+    let sprite_data = match load_sprites("data/sprites/character_sprites.json") {
+        Ok(data) => data,
+        Err(e) => {
+            println!("Failed to load sprites: {}", e);
+            return;
+        },
+    };
+    let colors: HashMap<char, Color> = sprite_data.color_palette.iter()
+        .filter_map(|(key, value)| Some((key.chars().next()?, parse_color(value).ok()?)))
+        .collect();
+    let mut sprite_names: Vec<&String> = sprite_data.sprites.iter()
+        .filter(|(_, sprite)| sprite.frames.contains_key("idle"))
+        .map(|(name, _)| name)
+        .collect();
+    sprite_names.sort();
+    let renderer = SpriteRenderer::new(sprite_data.clone());
+
+    println!("{:>8}  {:>16}  {:>16}  {:>16}  {:>16}", "monsters", "per-pixel draw", "per-pixel frame", "atlas draw", "atlas frame");
+    for count in MONSTER_COUNTS {
+        let mut results = Vec::new();
+        for method in [Method::PerPixel, Method::Atlas] {
+            let mut draw_time = 0.0;
+            let mut frame_time = 0.0;
+            for frame in 0..WARMUP_FRAMES + MEASURED_FRAMES {
+                let frame_start = Instant::now();
+                clear_background(DARKGREEN);
+                for i in 0..count {
+                    let position = vec2(
+                        20.0 + (i * 37 % 760) as f32 + (i % 7) as f32,
+                        20.0 + (i * 53 % 560) as f32,
+                    );
+                    let sprite_name = sprite_names[i % sprite_names.len()];
+                    match method {
+                        Method::PerPixel => draw_per_pixel(&sprite_data, &colors, sprite_name, position),
+                        Method::Atlas => {
+                            let facing = if i % 2 == 0 { Facing::Left } else { Facing::Right };
+                            renderer.draw_sprite_outlined(sprite_name, "idle", position, SPRITE_SCALE, facing.into(), BLACK)
+                        },
+                    }
+                }
+                let drawn = frame_start.elapsed().as_secs_f64();
+                next_frame().await;
+                if frame >= WARMUP_FRAMES {
+                    draw_time += drawn;
+                    frame_time += frame_start.elapsed().as_secs_f64();
+                }
+            }
+            let to_ms = |total: f64| total * 1000.0 / MEASURED_FRAMES as f64;
+            results.push((to_ms(draw_time), to_ms(frame_time)));
+        }
+        println!(
+            "{:>8}  {:>13.2} ms  {:>13.2} ms  {:>13.2} ms  {:>13.2} ms",
+            count, results[0].0, results[0].1, results[1].0, results[1].1,
+        );
+    }
+}
+
+// How sprites were drawn before the atlas: the silhouette eight times, one
+// screen pixel off in each direction, then one rectangle per sprite pixel
+fn draw_per_pixel(sprite_data: &SpriteData, colors: &HashMap<char, Color>, sprite_name: &str, position: Vec2) {
+    let sprite = &sprite_data.sprites[sprite_name];
+    let frame = &sprite.frames["idle"];
+    let (width, height) = sprite.size(&sprite_data.sprite_info);
+    let start_x = position.x - width as f32 * SPRITE_SCALE / 2.0;
+    let start_y = position.y - height as f32 * SPRITE_SCALE / 2.0;
+
+    let offsets = (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))).filter(|&offset| offset != (0, 0));
+    for (offset, silhouette) in offsets.map(|offset| (offset, true)).chain([((0, 0), false)]) {
+        for (y, row) in frame.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                if ch == '.' {
+                    continue;
+                }
+                let color = if silhouette { BLACK } else { colors.get(&ch).copied().unwrap_or(WHITE) };
+                draw_rectangle(
+                    start_x + offset.0 as f32 + x as f32 * SPRITE_SCALE,
+                    start_y + offset.1 as f32 + y as f32 * SPRITE_SCALE,
+                    SPRITE_SCALE,
+                    SPRITE_SCALE,
+                    color,
+                );
+            }
+        }
+    }
+}
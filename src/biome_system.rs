@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::color_system::parse_color;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameConfig {
@@ -34,6 +35,14 @@ pub struct PlayerConfig {
     pub starting_attack: i32,
     pub level_up_hp_bonus: i32,
     pub level_up_attack_bonus: i32,
+    #[serde(default)]
+    pub starting_inventory: Vec<String>,
+    #[serde(default = "default_xp_per_level")]
+    pub xp_per_level: i32,
+}
+
+fn default_xp_per_level() -> i32 {
+    100
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -50,13 +59,27 @@ pub struct ItemConfig {
     #[serde(default)]
     pub defense_bonus: Option<i32>,
     pub price: i32,
+    #[serde(default)]
+    pub sprite: Option<String>,
 }
 
 pub fn load_game_config(path: &str) -> Result<GameConfig, String> {
     match std::fs::read_to_string(path) {
         Ok(contents) => {
             match serde_json::from_str::<GameConfig>(&contents) {
-                Ok(config) => Ok(config),
+                Ok(config) => {
+                    // Levelling up loops until XP runs short, which never
+                    // happens with a threshold of zero or less
+                    if config.player_config.xp_per_level <= 0 {
+                        return Err(format!("xp_per_level must be above 0, not {}", config.player_config.xp_per_level));
+                    }
+                    for (name, biome) in &config.biomes {
+                        if let Err(e) = parse_color(&biome.ambient_color) {
+                            return Err(format!("Biome {} ambient color: {}", name, e));
+                        }
+                    }
+                    Ok(config)
+                },
                 Err(e) => Err(format!("JSON parsing error: {}", e)),
             }
         },
@@ -1,7 +1,12 @@
 use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::{MapData, Monster, ActiveMonster, load_map};
+use crate::{MapData, MapLayer, Monster, MonsterSpawn, ActiveMonster, TileType, load_map};
+use crate::color_system::parse_color;
+use crate::shop_system::MerchantData;
+use crate::dialogue_system::NpcData;
+use crate::sprite_system::{Animator, Heading};
+use crate::save_system::{ChunkState, MonsterState, PlayerSave, SpawnedMonsterState};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WorldConfig {
@@ -10,6 +15,17 @@ pub struct WorldConfig {
     pub chunks: HashMap<String, ChunkConfig>,
     pub spawn_chunk: String,
     pub spawn_position: SpawnPosition,
+    // Maps reached only through warps; load_world_config also lists them in
+    // `chunks`, parked in a column to the right of the grid
+    #[serde(default)]
+    pub interiors: HashMap<String, InteriorConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InteriorConfig {
+    pub biome: String,
+    pub map_file: String,
+    pub name: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -43,7 +59,105 @@ pub struct Chunk {
     pub world_y: usize,
     pub map_data: MapData,
     pub active_monsters: Vec<ActiveMonster>,
-    pub is_loaded: bool,
+    // None until baked, and again whenever the tiles it shows change
+    render: Option<ChunkRender>,
+}
+
+// The chunk's ground and decoration drawn once into a texture. Tiles with
+// animated pictures can't be baked and are drawn over it every frame.
+struct ChunkRender {
+    target: RenderTarget,
+    live_tiles: Vec<(usize, usize)>,
+}
+
+impl Chunk {
+    pub fn capture_state(&self) -> ChunkState {
+        ChunkState {
+            monsters: self.active_monsters.iter().filter(|monster| monster.respawns).map(|monster| MonsterState {
+                position: [monster.position.x, monster.position.y],
+                current_hp: monster.current_hp,
+                is_dead: monster.is_dead,
+                respawn_timer: monster.respawn_timer,
+            }).collect(),
+            spawned: self.active_monsters.iter().filter(|monster| !monster.respawns && !monster.is_dead).map(|monster| SpawnedMonsterState {
+                monster_type: monster.data.name.clone(),
+                position: [monster.position.x, monster.position.y],
+                current_hp: monster.current_hp,
+            }).collect(),
+        }
+    }
+    
+    pub fn apply_state(&mut self, state: &ChunkState, monster_templates: &[Monster]) {
+        for (monster, saved) in self.active_monsters.iter_mut().zip(&state.monsters) {
+            monster.position = vec2(saved.position[0], saved.position[1]);
+            monster.current_hp = saved.current_hp;
+            monster.is_dead = saved.is_dead;
+            monster.respawn_timer = saved.respawn_timer;
+        }
+        
+        // Event spawns are untiered, as when the trigger made them
+        for saved in &state.spawned {
+            match monster_templates.iter().find(|m| m.name == saved.monster_type && m.tier.is_none()) {
+                Some(template) => {
+                    let mut monster = new_monster(template, vec2(saved.position[0], saved.position[1]), false);
+                    monster.current_hp = saved.current_hp;
+                    self.active_monsters.push(monster);
+                },
+                None => println!("Unknown monster '{}' spawned in chunk {}", saved.monster_type, self.id),
+            }
+        }
+    }
+}
+
+// A monster at full health, standing at `position`
+fn new_monster(template: &Monster, position: Vec2, respawns: bool) -> ActiveMonster {
+    ActiveMonster {
+        data: template.clone(),
+        position,
+        current_hp: template.hp,
+        is_dead: false,
+        respawn_timer: 0.0,
+        respawns,
+        path: Vec::new(),
+        repath_timer: 0.0,
+        animator: Animator::new(),
+        heading: Heading::default(),
+    }
+}
+
+// How a map layer is drawn
+#[derive(Clone, Copy)]
+enum LayerStyle {
+    Ground,
+    Decoration,
+    // Tiles near `reveal` are drawn see-through
+    Overhead { reveal: Option<Vec2> },
+}
+
+impl LayerStyle {
+    fn layer(self) -> MapLayer {
+        match self {
+            LayerStyle::Ground => MapLayer::Ground,
+            LayerStyle::Decoration => MapLayer::Decoration,
+            LayerStyle::Overhead { .. } => MapLayer::Overhead,
+        }
+    }
+}
+
+// Bit order of the autotile neighbour mask: north, east, south, west
+const AUTOTILE_NEIGHBORS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+const DECORATION_INSET: f32 = 0.15; // fraction of a tile left around decorations
+const OVERHEAD_REVEAL_RADIUS: f32 = 2.5; // in tiles
+const OVERHEAD_REVEAL_ALPHA: f32 = 0.35;
+// Extra pathfinding cost per point of damage per second, so monsters avoid lava
+const HAZARD_COST_PER_DPS: f32 = 0.2;
+
+// The grid chunks that were loaded when the player went indoors
+struct IndoorState {
+    interior: String,
+    outdoor_chunk: String,
+    paused_chunks: Vec<String>,
 }
 
 pub struct ChunkManager {
@@ -52,6 +166,11 @@ pub struct ChunkManager {
     pub loaded_chunks: Vec<String>,
     pub current_chunk: String,
     pub chunk_pixel_size: f32,
+    // State of chunks that were unloaded, reapplied when they stream back in
+    chunk_states: HashMap<String, ChunkState>,
+    indoors: Option<IndoorState>,
+    // Tileset images by path; None if the image couldn't be loaded
+    tile_textures: HashMap<String, Option<Texture2D>>,
 }
 
 impl ChunkManager {
@@ -64,6 +183,9 @@ impl ChunkManager {
             loaded_chunks: Vec::new(),
             current_chunk: String::new(),
             chunk_pixel_size,
+            chunk_states: HashMap::new(),
+            indoors: None,
+            tile_textures: HashMap::new(),
         }
     }
     
@@ -87,23 +209,64 @@ impl ChunkManager {
         )
     }
     
+    // Tile coordinates inside a chunk's map to world position, same convention as monster spawns
+    pub fn chunk_tile_to_world(&self, chunk: &Chunk, tile_x: f32, tile_y: f32) -> Vec2 {
+        let tile_size = chunk.map_data.map_info.tile_size;
+        self.chunk_to_world_coords(chunk.world_x, chunk.world_y) + vec2(tile_x * tile_size, tile_y * tile_size)
+    }
+    
+    // Like chunk_tile_to_world, but also works for chunks that aren't loaded
+    pub fn chunk_id_tile_to_world(&self, chunk_id: &str, tile_x: f32, tile_y: f32) -> Option<Vec2> {
+        let config = self.world_config.chunks.get(chunk_id)?;
+        let tile_size = self.world_config.world_info.tile_size;
+        Some(self.chunk_to_world_coords(config.world_x, config.world_y) + vec2(tile_x * tile_size, tile_y * tile_size))
+    }
+    
+    // Adds a one-off monster that is removed for good once killed
+    pub fn spawn_monster(&mut self, chunk_id: &str, template: &Monster, world_pos: Vec2) -> bool {
+        match self.chunks.get_mut(chunk_id) {
+            Some(chunk) => {
+                chunk.active_monsters.push(new_monster(template, world_pos, false));
+                true
+            },
+            None => false,
+        }
+    }
+    
     pub fn get_chunk_at_position(&self, world_pos: Vec2) -> Option<String> {
+        self.chunk_id_at_position(world_pos).map(str::to_string)
+    }
+    
+    fn chunk_id_at_position(&self, world_pos: Vec2) -> Option<&str> {
+        // Indoors there is only the one map
+        if let Some(indoors) = &self.indoors {
+            return Some(&indoors.interior);
+        }
+        
         let (chunk_x, chunk_y) = self.world_to_chunk_coords(world_pos);
         
         if chunk_y < self.world_config.chunk_layout.len() &&
            chunk_x < self.world_config.chunk_layout[chunk_y].len() {
-            Some(self.world_config.chunk_layout[chunk_y][chunk_x].clone())
+            Some(&self.world_config.chunk_layout[chunk_y][chunk_x])
         } else {
             None
         }
     }
     
-    pub fn update_loaded_chunks(&mut self, player_pos: Vec2, monster_templates: &[Monster]) {
+    // Returns true when the player crossed into a different chunk
+    pub fn update_loaded_chunks(&mut self, player_pos: Vec2, monster_templates: &[Monster]) -> bool {
+        // The grid is paused while the player is indoors
+        if self.indoors.is_some() {
+            return false;
+        }
+        
         let current_chunk_id = self.get_chunk_at_position(player_pos);
+        let mut entered_new_chunk = false;
         
         if let Some(chunk_id) = current_chunk_id {
             if chunk_id != self.current_chunk {
                 self.current_chunk = chunk_id.clone();
+                entered_new_chunk = true;
                 println!("Entered chunk: {}", chunk_id);
             }
             
@@ -129,6 +292,87 @@ impl ChunkManager {
                 }
             }
         }
+        
+        entered_new_chunk
+    }
+    
+    pub fn snapshot_chunk_states(&self) -> HashMap<String, ChunkState> {
+        let mut states = self.chunk_states.clone();
+        for (chunk_id, chunk) in &self.chunks {
+            states.insert(chunk_id.clone(), chunk.capture_state());
+        }
+        states
+    }
+    
+    // Drops every loaded chunk and streams the world back in around the player using saved state
+    pub fn restore(&mut self, states: HashMap<String, ChunkState>, player: &PlayerSave, monster_templates: &[Monster]) {
+        self.chunks.clear();
+        self.loaded_chunks.clear();
+        self.indoors = None;
+        self.chunk_states = states;
+        
+        let player_pos = vec2(player.position[0], player.position[1]);
+        match &player.outdoor_chunk {
+            Some(outdoor_chunk) if self.is_interior(&player.chunk) => {
+                // Saved indoors: bring back the grid around the door first
+                let half = self.world_config.world_info.chunk_size as f32 / 2.0;
+                let outside = self.chunk_id_tile_to_world(outdoor_chunk, half, half).unwrap_or(player_pos);
+                self.current_chunk = self.get_chunk_at_position(outside).unwrap_or_default();
+                self.update_loaded_chunks(outside, monster_templates);
+                self.enter_interior(&player.chunk, monster_templates);
+            },
+            _ => {
+                self.current_chunk = self.get_chunk_at_position(player_pos).unwrap_or_default();
+                self.update_loaded_chunks(player_pos, monster_templates);
+            },
+        }
+    }
+    
+    pub fn is_interior(&self, chunk_id: &str) -> bool {
+        self.world_config.interiors.contains_key(chunk_id)
+    }
+    
+    // The grid chunk the player left to go indoors
+    pub fn outdoor_chunk(&self) -> Option<&str> {
+        self.indoors.as_ref().map(|indoors| indoors.outdoor_chunk.as_str())
+    }
+    
+    fn enter_interior(&mut self, interior: &str, monster_templates: &[Monster]) {
+        if self.indoors.is_some() {
+            self.leave_interior();
+        }
+        
+        let paused_chunks = std::mem::take(&mut self.loaded_chunks);
+        self.load_chunk(interior, monster_templates);
+        self.indoors = Some(IndoorState {
+            interior: interior.to_string(),
+            outdoor_chunk: std::mem::replace(&mut self.current_chunk, interior.to_string()),
+            paused_chunks,
+        });
+    }
+    
+    fn leave_interior(&mut self) {
+        if let Some(indoors) = self.indoors.take() {
+            self.unload_chunk(&indoors.interior);
+            self.loaded_chunks = indoors.paused_chunks;
+            self.current_chunk = indoors.outdoor_chunk;
+        }
+    }
+    
+    // Moves between the grid and interiors; returns the world position of the target tile
+    pub fn warp(&mut self, chunk_id: &str, tile_x: f32, tile_y: f32, monster_templates: &[Monster]) -> Option<Vec2> {
+        let pos = self.chunk_id_tile_to_world(chunk_id, tile_x, tile_y)?;
+        
+        if self.is_interior(chunk_id) {
+            if self.indoors.as_ref().is_none_or(|indoors| indoors.interior != chunk_id) {
+                self.enter_interior(chunk_id, monster_templates);
+            }
+        } else {
+            self.leave_interior();
+            self.update_loaded_chunks(pos, monster_templates);
+        }
+        
+        Some(pos)
     }
     
     fn get_nearby_chunks(&self, center_chunk_id: &str) -> Vec<String> {
@@ -160,14 +404,17 @@ impl ChunkManager {
     fn load_chunk(&mut self, chunk_id: &str, monster_templates: &[Monster]) {
         if let Some(config) = self.world_config.chunks.get(chunk_id) {
             match load_map(&config.map_file) {
-                Ok(map_data) => {
+                Ok(mut map_data) => {
+                    if let Some(spawns) = &mut map_data.monster_spawns {
+                        validate_spawn_tiers(chunk_id, spawns, monster_templates);
+                    }
                     let mut chunk = Chunk {
                         id: chunk_id.to_string(),
                         world_x: config.world_x,
                         world_y: config.world_y,
                         map_data,
                         active_monsters: Vec::new(),
-                        is_loaded: true,
+                        render: None,
                     };
                     
                     // Create monsters for this chunk
@@ -175,25 +422,24 @@ impl ChunkManager {
                         let world_offset = self.chunk_to_world_coords(config.world_x, config.world_y);
                         
                         for spawn in spawns {
-                            if let Some(template) = monster_templates.iter().find(|m| m.name == spawn.monster_type) {
+                            if let Some(template) = monster_templates.iter().find(|m| m.name == spawn.monster_type && m.tier == spawn.tier) {
                                 let world_pos = world_offset + vec2(
                                     spawn.x * chunk.map_data.map_info.tile_size,
                                     spawn.y * chunk.map_data.map_info.tile_size
                                 );
                                 
-                                chunk.active_monsters.push(ActiveMonster {
-                                    data: template.clone(),
-                                    position: world_pos,
-                                    current_hp: template.hp,
-                                    is_dead: false,
-                                    respawn_timer: 0.0,
-                                });
+                                chunk.active_monsters.push(new_monster(template, world_pos, true));
                             }
                         }
                     }
                     
+                    if let Some(state) = self.chunk_states.get(chunk_id) {
+                        chunk.apply_state(state, monster_templates);
+                    }
+                    
                     self.chunks.insert(chunk_id.to_string(), chunk);
                     self.loaded_chunks.push(chunk_id.to_string());
+                    self.invalidate_neighbors(config.world_x, config.world_y);
                     println!("Loaded chunk: {}", chunk_id);
                 },
                 Err(e) => {
@@ -204,92 +450,402 @@ impl ChunkManager {
     }
     
     fn unload_chunk(&mut self, chunk_id: &str) {
-        if self.chunks.remove(chunk_id).is_some() {
+        if let Some(chunk) = self.chunks.remove(chunk_id) {
+            self.chunk_states.insert(chunk_id.to_string(), chunk.capture_state());
             self.loaded_chunks.retain(|id| id != chunk_id);
-            println!("Unloaded chunk: {}", chunk_id);
+            self.invalidate_neighbors(chunk.world_x, chunk.world_y);
+            println!("Unloaded chunk: {}", chunk.id);
         }
     }
     
     pub fn is_position_walkable(&self, world_pos: Vec2) -> bool {
-        let chunk_id = match self.get_chunk_at_position(world_pos) {
-            Some(id) => id,
-            None => return false,
-        };
-        
-        let chunk = match self.chunks.get(&chunk_id) {
-            Some(c) => c,
-            None => return false,
-        };
+        match self.chunk_at_position(world_pos) {
+            Some(chunk) => {
+                let world_offset = self.chunk_to_world_coords(chunk.world_x, chunk.world_y);
+                chunk.map_data.is_walkable_at(world_pos - world_offset)
+            },
+            None => false,
+        }
+    }
+    
+    pub fn surface_at(&self, world_pos: Vec2) -> Option<&TileType> {
+        let (chunk, tile_x, tile_y) = self.tile_at_position(world_pos)?;
+        chunk.map_data.surface_at(tile_x, tile_y)
+    }
+    
+    // Step cost for pathfinding, None where nothing can walk
+    pub fn movement_cost(&self, world_pos: Vec2) -> Option<f32> {
+        let (chunk, tile_x, tile_y) = self.tile_at_position(world_pos)?;
+        if !chunk.map_data.is_walkable(tile_x, tile_y) {
+            return None;
+        }
+        let tile = chunk.map_data.surface_at(tile_x, tile_y)?;
+        Some(1.0 / tile.speed_multiplier.clamp(0.1, 2.0) + tile.damage_per_second * HAZARD_COST_PER_DPS)
+    }
+    
+    // Whether anything thrown or shot from `from` would reach `to`
+    pub fn has_clear_line(&self, from: Vec2, to: Vec2) -> bool {
+        let step = self.world_config.world_info.tile_size / 4.0;
+        let steps = ((to - from).length() / step).ceil() as usize;
+        (1..steps).all(|i| {
+            let pos = from.lerp(to, i as f32 / steps as f32);
+            match self.tile_at_position(pos) {
+                Some((chunk, tile_x, tile_y)) => !chunk.map_data.blocks_projectiles(tile_x, tile_y),
+                None => false,
+            }
+        })
+    }
+    
+    // True while the player stands under a roof or treetop
+    pub fn is_under_overhead(&self, world_pos: Vec2) -> bool {
+        match self.tile_at_position(world_pos) {
+            Some((chunk, tile_x, tile_y)) => {
+                chunk.map_data.tile_at(MapLayer::Overhead, tile_x, tile_y).is_some()
+            },
+            None => false,
+        }
+    }
+    
+    fn chunk_at_position(&self, world_pos: Vec2) -> Option<&Chunk> {
+        self.chunks.get(self.chunk_id_at_position(world_pos)?)
+    }
+    
+    fn tile_at_position(&self, world_pos: Vec2) -> Option<(&Chunk, usize, usize)> {
+        let chunk = self.chunk_at_position(world_pos)?;
         
         // Convert world position to local chunk position
         let world_offset = self.chunk_to_world_coords(chunk.world_x, chunk.world_y);
-        let local_pos = world_pos - world_offset;
+        let (tile_x, tile_y) = chunk.map_data.tile_coords(world_pos - world_offset)?;
+        Some((chunk, tile_x, tile_y))
+    }
+    
+    // Loads the tileset images of newly loaded chunks. Kept out of chunk
+    // loading so that doesn't need a graphics context.
+    pub fn load_tile_textures(&mut self) {
+        let mut loaded_any = false;
+        for chunk_id in &self.loaded_chunks {
+            let chunk = match self.chunks.get(chunk_id) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            for tileset in chunk.map_data.tilesets.values() {
+                if self.tile_textures.contains_key(&tileset.image) {
+                    continue;
+                }
+                let texture = match load_tileset_image(&tileset.image) {
+                    Ok(texture) => Some(texture),
+                    Err(e) => {
+                        println!("{}; drawing its tiles as colors", e);
+                        None
+                    },
+                };
+                self.tile_textures.insert(tileset.image.clone(), texture);
+                loaded_any = true;
+            }
+        }
         
-        // Convert to tile coordinates
+        // Chunks baked before the image was there show colors instead
+        if loaded_any {
+            for chunk in self.chunks.values_mut() {
+                chunk.render = None;
+            }
+        }
+    }
+    
+    // Autotile edges along a chunk's border depend on its neighbours, so they
+    // are baked again when a chunk next to them comes or goes
+    fn invalidate_neighbors(&mut self, world_x: usize, world_y: usize) {
+        for chunk in self.chunks.values_mut() {
+            if chunk.world_x.abs_diff(world_x) + chunk.world_y.abs_diff(world_y) <= 1 {
+                chunk.render = None;
+            }
+        }
+    }
+    
+    // Renders the ground and decoration of every loaded chunk that has no
+    // up-to-date texture. Call once a frame before draw_chunks.
+    pub fn bake_chunks(&mut self) {
+        let stale: Vec<String> = self.loaded_chunks.iter()
+            .filter(|id| self.chunks.get(*id).is_some_and(|chunk| chunk.render.is_none()))
+            .cloned()
+            .collect();
+        for chunk_id in stale {
+            let render = match self.chunks.get(&chunk_id) {
+                Some(chunk) => self.bake_chunk(chunk),
+                None => continue,
+            };
+            if let Some(chunk) = self.chunks.get_mut(&chunk_id) {
+                chunk.render = Some(render);
+            }
+        }
+    }
+    
+    fn bake_chunk(&self, chunk: &Chunk) -> ChunkRender {
+        let grid = &chunk.map_data.grid;
         let tile_size = chunk.map_data.map_info.tile_size;
-        let tile_x = (local_pos.x / tile_size) as usize;
-        let tile_y = (local_pos.y / tile_size) as usize;
+        let size = vec2(grid.width as f32 * tile_size, grid.height as f32 * tile_size);
         
-        if tile_y >= chunk.map_data.layout.len() || tile_x >= chunk.map_data.map_info.width {
-            return false;
-        }
+        let target = render_target(size.x as u32, size.y as u32);
+        target.texture.set_filter(FilterMode::Nearest);
+        // Render targets are stored bottom row first, hence the positive y zoom
+        set_camera(&Camera2D {
+            zoom: vec2(2.0 / size.x, 2.0 / size.y),
+            target: size / 2.0,
+            render_target: Some(target.clone()),
+            ..Default::default()
+        });
+        clear_background(Color::new(0.0, 0.0, 0.0, 0.0));
         
-        if let Some(row) = chunk.map_data.layout.get(tile_y) {
-            if let Some(tile_char) = row.chars().nth(tile_x) {
-                let tile_key = tile_char.to_string();
-                if let Some(tile_type) = chunk.map_data.tile_types.get(&tile_key) {
-                    return tile_type.walkable;
+        let mut live_tiles = Vec::new();
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let animated = [MapLayer::Ground, MapLayer::Decoration].into_iter().any(|layer| {
+                    grid.tile_at(layer, x, y)
+                        .and_then(|tile| tile.texture.as_ref())
+                        .is_some_and(|texture| !texture.frames.is_empty())
+                });
+                if animated {
+                    live_tiles.push((x, y));
                 }
+                let pos = vec2(x as f32 * tile_size, y as f32 * tile_size);
+                self.draw_tile(chunk, LayerStyle::Ground, x, y, pos, 1.0);
+                self.draw_tile(chunk, LayerStyle::Decoration, x, y, pos, 1.0);
             }
         }
         
-        false
+        set_default_camera();
+        ChunkRender { target, live_tiles }
     }
     
     pub fn draw_chunks(&self, camera_offset: Vec2, _sprite_renderer: &crate::sprite_system::SpriteRenderer) {
-        // Draw all loaded chunks
+        // Draw the baked ground and decoration of all loaded chunks
+        for chunk_id in &self.loaded_chunks {
+            let chunk = match self.chunks.get(chunk_id) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let render = match &chunk.render {
+                Some(render) => render,
+                None => {
+                    self.draw_layer(chunk, camera_offset, LayerStyle::Ground);
+                    self.draw_layer(chunk, camera_offset, LayerStyle::Decoration);
+                    continue;
+                },
+            };
+            
+            let world_offset = self.chunk_to_world_coords(chunk.world_x, chunk.world_y);
+            let texture = &render.target.texture;
+            draw_texture_ex(texture, world_offset.x + camera_offset.x, world_offset.y + camera_offset.y, WHITE, DrawTextureParams {
+                dest_size: Some(vec2(texture.width(), texture.height())),
+                ..Default::default()
+            });
+            
+            let tile_size = chunk.map_data.map_info.tile_size;
+            for &(x, y) in &render.live_tiles {
+                let screen_pos = world_offset + camera_offset + vec2(x as f32 * tile_size, y as f32 * tile_size);
+                if is_on_screen(screen_pos, tile_size) {
+                    self.draw_tile(chunk, LayerStyle::Ground, x, y, screen_pos, 1.0);
+                    self.draw_tile(chunk, LayerStyle::Decoration, x, y, screen_pos, 1.0);
+                }
+            }
+        }
+    }
+    
+    // Called after entities are drawn. While the player is hidden under an
+    // overhead tile, the ones around them turn see-through.
+    pub fn draw_overhead(&self, camera_offset: Vec2, player_pos: Vec2) {
+        let reveal = if self.is_under_overhead(player_pos) { Some(player_pos) } else { None };
         for chunk_id in &self.loaded_chunks {
             if let Some(chunk) = self.chunks.get(chunk_id) {
-                self.draw_chunk(chunk, camera_offset);
+                self.draw_layer(chunk, camera_offset, LayerStyle::Overhead { reveal });
             }
         }
     }
     
-    fn draw_chunk(&self, chunk: &Chunk, camera_offset: Vec2) {
+    fn draw_layer(&self, chunk: &Chunk, camera_offset: Vec2, style: LayerStyle) {
         let world_offset = self.chunk_to_world_coords(chunk.world_x, chunk.world_y);
+        let grid = &chunk.map_data.grid;
         let tile_size = chunk.map_data.map_info.tile_size;
         
-        for (y, row) in chunk.map_data.layout.iter().enumerate() {
-            for (x, tile_char) in row.chars().enumerate() {
-                let tile_key = tile_char.to_string();
-                if let Some(tile_type) = chunk.map_data.tile_types.get(&tile_key) {
-                    let world_pos = world_offset + vec2(x as f32 * tile_size, y as f32 * tile_size);
-                    let screen_pos = world_pos + camera_offset;
-                    
-                    // Only draw tiles that are on screen
-                    if screen_pos.x > -tile_size && screen_pos.x < screen_width() + tile_size &&
-                       screen_pos.y > -tile_size && screen_pos.y < screen_height() + tile_size {
-                        draw_rectangle(
-                            screen_pos.x,
-                            screen_pos.y,
-                            tile_size,
-                            tile_size,
-                            crate::string_to_color(&tile_type.color)
-                        );
-                        
-                        // Draw tile borders for better visibility
-                        draw_rectangle_lines(
-                            screen_pos.x,
-                            screen_pos.y,
-                            tile_size,
-                            tile_size,
-                            1.0,
-                            Color::new(0.0, 0.0, 0.0, 0.3)
-                        );
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let world_pos = world_offset + vec2(x as f32 * tile_size, y as f32 * tile_size);
+                let screen_pos = world_pos + camera_offset;
+                if !is_on_screen(screen_pos, tile_size) {
+                    continue;
+                }
+                
+                // Overhead tiles near a hidden player fade out
+                let mut alpha = 1.0;
+                if let LayerStyle::Overhead { reveal: Some(pos) } = style {
+                    let center = world_pos + vec2(tile_size / 2.0, tile_size / 2.0);
+                    if (center - pos).length() <= OVERHEAD_REVEAL_RADIUS * tile_size {
+                        alpha = OVERHEAD_REVEAL_ALPHA;
                     }
                 }
+                
+                self.draw_tile(chunk, style, x, y, screen_pos, alpha);
+            }
+        }
+    }
+    
+    fn draw_tile(&self, chunk: &Chunk, style: LayerStyle, x: usize, y: usize, screen_pos: Vec2, alpha: f32) {
+        let tile_type = match chunk.map_data.tile_at(style.layer(), x, y) {
+            Some(tile_type) => tile_type,
+            None => return,
+        };
+        let tile_size = chunk.map_data.map_info.tile_size;
+        
+        if self.draw_tile_texture(chunk, tile_type, screen_pos, tile_size, alpha) {
+            self.draw_autotile(chunk, style, x, y, tile_type, screen_pos, alpha);
+            return;
+        }
+        
+        // Checked when the map loaded
+        let mut color = parse_color(&tile_type.color).unwrap_or(WHITE);
+        color.a *= alpha;
+        match style {
+            LayerStyle::Ground => {
+                draw_rectangle(screen_pos.x, screen_pos.y, tile_size, tile_size, color);
+                
+                // Draw tile borders for better visibility. Pre-darkened rather
+                // than translucent so baking doesn't leave see-through seams.
+                draw_rectangle_lines(
+                    screen_pos.x,
+                    screen_pos.y,
+                    tile_size,
+                    tile_size,
+                    1.0,
+                    Color::new(color.r * 0.7, color.g * 0.7, color.b * 0.7, color.a)
+                );
+            },
+            LayerStyle::Decoration => {
+                // Inset so the ground underneath stays visible
+                let inset = tile_size * DECORATION_INSET;
+                draw_rectangle(
+                    screen_pos.x + inset,
+                    screen_pos.y + inset,
+                    tile_size - inset * 2.0,
+                    tile_size - inset * 2.0,
+                    color
+                );
+            },
+            LayerStyle::Overhead { .. } => {
+                draw_rectangle(screen_pos.x, screen_pos.y, tile_size, tile_size, color);
+            },
+        }
+        self.draw_autotile(chunk, style, x, y, tile_type, screen_pos, alpha);
+    }
+    
+    // Draws the edge overlay matching which of the four neighbours share the
+    // tile's terrain, looking into the next chunk at the map's edges
+    #[allow(clippy::too_many_arguments)]
+    fn draw_autotile(&self, chunk: &Chunk, style: LayerStyle, x: usize, y: usize, tile_type: &TileType, screen_pos: Vec2, alpha: f32) {
+        let autotile = match &tile_type.autotile {
+            Some(autotile) => autotile,
+            None => return,
+        };
+        let mut mask = 0;
+        for (bit, (dx, dy)) in AUTOTILE_NEIGHBORS.iter().enumerate() {
+            if self.neighbor_terrain(chunk, style, x as i32 + dx, y as i32 + dy).is_none_or(|terrain| terrain == autotile.terrain) {
+                mask |= 1 << bit;
             }
         }
+        
+        let tile_size = chunk.map_data.map_info.tile_size;
+        self.draw_tileset_tile(chunk, &autotile.tileset, autotile.first + mask, screen_pos, tile_size, alpha);
+    }
+    
+    // Terrain of a tile in the same layer, "" for tiles that don't autotile.
+    // None where there's nothing to compare against, which counts as joined
+    // so map and world edges don't get a border.
+    fn neighbor_terrain<'a>(&'a self, chunk: &'a Chunk, style: LayerStyle, x: i32, y: i32) -> Option<&'a str> {
+        let map = &chunk.map_data;
+        fn terrain(map: &MapData, style: LayerStyle, x: usize, y: usize) -> &str {
+            let tile = map.tile_at(style.layer(), x, y);
+            tile.and_then(|tile| tile.autotile.as_ref()).map_or("", |autotile| autotile.terrain.as_str())
+        }
+        
+        if x >= 0 && y >= 0 && (x as usize) < map.grid.width && (y as usize) < map.grid.height {
+            return Some(terrain(map, style, x as usize, y as usize));
+        }
+        
+        let tile_size = map.map_info.tile_size;
+        let world_pos = self.chunk_to_world_coords(chunk.world_x, chunk.world_y) +
+            vec2((x as f32 + 0.5) * tile_size, (y as f32 + 0.5) * tile_size);
+        match self.tile_at_position(world_pos) {
+            Some((other, tile_x, tile_y)) if other.id != chunk.id => Some(terrain(&other.map_data, style, tile_x, tile_y)),
+            _ => None,
+        }
+    }
+    
+    // Draws the tile's picture; false means there is none and the color is used
+    fn draw_tile_texture(&self, chunk: &Chunk, tile_type: &TileType, screen_pos: Vec2, tile_size: f32, alpha: f32) -> bool {
+        let texture = match &tile_type.texture {
+            Some(texture) => texture,
+            None => return false,
+        };
+        self.draw_tileset_tile(chunk, &texture.tileset, texture.tile_at(get_time() as f32), screen_pos, tile_size, alpha)
+    }
+    
+    fn draw_tileset_tile(&self, chunk: &Chunk, tileset: &str, index: usize, screen_pos: Vec2, tile_size: f32, alpha: f32) -> bool {
+        let tileset = match chunk.map_data.tilesets.get(tileset) {
+            Some(tileset) => tileset,
+            None => return false,
+        };
+        let image = match self.tile_textures.get(&tileset.image) {
+            Some(Some(image)) => image,
+            _ => return false,
+        };
+        
+        let columns = ((image.width() / tileset.tile_size) as usize).max(1);
+        let source = Rect::new(
+            (index % columns) as f32 * tileset.tile_size,
+            (index / columns) as f32 * tileset.tile_size,
+            tileset.tile_size,
+            tileset.tile_size,
+        );
+        
+        draw_texture_ex(image, screen_pos.x, screen_pos.y, Color::new(1.0, 1.0, 1.0, alpha), DrawTextureParams {
+            dest_size: Some(vec2(tile_size, tile_size)),
+            source: Some(source),
+            ..Default::default()
+        });
+        true
+    }
+    
+    pub fn find_merchant_near(&self, world_pos: Vec2, range: f32) -> Option<&MerchantData> {
+        let mut nearest: Option<(&MerchantData, f32)> = None;
+        
+        for chunk_id in &self.loaded_chunks {
+            if let Some(chunk) = self.chunks.get(chunk_id) {
+                for merchant in chunk.map_data.merchants.iter().flatten() {
+                    let distance = (self.chunk_tile_to_world(chunk, merchant.x, merchant.y) - world_pos).length();
+                    if distance <= range && nearest.is_none_or(|(_, best)| distance < best) {
+                        nearest = Some((merchant, distance));
+                    }
+                }
+            }
+        }
+        
+        nearest.map(|(merchant, _)| merchant)
+    }
+    
+    pub fn find_npc_near(&self, world_pos: Vec2, range: f32) -> Option<&NpcData> {
+        let mut nearest: Option<(&NpcData, f32)> = None;
+        
+        for chunk_id in &self.loaded_chunks {
+            if let Some(chunk) = self.chunks.get(chunk_id) {
+                for npc in chunk.map_data.npcs.iter().flatten() {
+                    let distance = (self.chunk_tile_to_world(chunk, npc.x, npc.y) - world_pos).length();
+                    if distance <= range && nearest.is_none_or(|(_, best)| distance < best) {
+                        nearest = Some((npc, distance));
+                    }
+                }
+            }
+        }
+        
+        nearest.map(|(npc, _)| npc)
     }
     
     pub fn get_loaded_chunk_ids(&self) -> Vec<String> {
@@ -301,14 +857,66 @@ impl ChunkManager {
     }
 }
 
+fn is_on_screen(screen_pos: Vec2, tile_size: f32) -> bool {
+    screen_pos.x > -tile_size && screen_pos.x < screen_width() + tile_size &&
+        screen_pos.y > -tile_size && screen_pos.y < screen_height() + tile_size
+}
+
+// Spawns naming a tier no monster has (misspelt, or tiers.yaml didn't
+// load) get the plain monster instead of none at all
+fn validate_spawn_tiers(chunk_id: &str, spawns: &mut [MonsterSpawn], monster_templates: &[Monster]) {
+    for spawn in spawns {
+        let Some(tier) = &spawn.tier else { continue };
+        if !monster_templates.iter().any(|m| m.tier.as_ref() == Some(tier)) {
+            println!("Unknown monster tier '{}' for {} in chunk {}", tier, spawn.monster_type, chunk_id);
+            spawn.tier = None;
+        }
+    }
+}
+
+fn load_tileset_image(path: &str) -> Result<Texture2D, String> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return Err(format!("Tileset read error: {}: {}", path, e)),
+    };
+    match Image::from_file_with_format(&bytes, None) {
+        Ok(image) => {
+            let texture = Texture2D::from_image(&image);
+            texture.set_filter(FilterMode::Nearest);
+            Ok(texture)
+        },
+        Err(e) => Err(format!("Tileset image error: {}: {}", path, e)),
+    }
+}
+
 pub fn load_world_config(path: &str) -> Result<WorldConfig, String> {
-    match std::fs::read_to_string(path) {
+    let mut config = match std::fs::read_to_string(path) {
         Ok(contents) => {
             match serde_json::from_str::<WorldConfig>(&contents) {
-                Ok(config) => Ok(config),
-                Err(e) => Err(format!("JSON parsing error: {}", e)),
+                Ok(config) => config,
+                Err(e) => return Err(format!("JSON parsing error: {}", e)),
             }
         },
-        Err(e) => Err(format!("File read error: {}", e)),
+        Err(e) => return Err(format!("File read error: {}", e)),
+    };
+    
+    // Give each interior its own chunk slot one column past the grid, so
+    // positions inside it never overlap the seamless world
+    let mut interior_ids: Vec<String> = config.interiors.keys().cloned().collect();
+    interior_ids.sort();
+    for (i, id) in interior_ids.into_iter().enumerate() {
+        if config.chunks.contains_key(&id) {
+            return Err(format!("Interior {} has the same id as a chunk", id));
+        }
+        let interior = config.interiors[&id].clone();
+        config.chunks.insert(id, ChunkConfig {
+            world_x: config.world_info.chunks_x + 1,
+            world_y: i,
+            biome: interior.biome,
+            map_file: interior.map_file,
+            name: interior.name,
+        });
     }
+    
+    Ok(config)
 }
\ No newline at end of file
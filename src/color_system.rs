@@ -0,0 +1,272 @@
+use macroquad::prelude::*;
+
+// Names whose CSS value would change how existing maps look, kept at the
+// shades the game drew them with. They're looked up before CSS_COLORS, so
+// these six, and only these, don't give their CSS values: CSS green is about
+// as dark as the old darkgreen, so grass and trees would look alike; CSS
+// darkgray is lighter than CSS gray; CSS brown is a red that would turn dirt
+// paths red; and CSS pink is so pale the forest flowers would wash out.
+const GAME_COLORS: [(&str, Color); 6] = [
+    ("green", GREEN),
+    ("darkgreen", Color::new(0.0, 0.5, 0.0, 1.0)),
+    ("gray", GRAY),
+    ("darkgray", DARKGRAY),
+    ("brown", Color::new(0.5, 0.25, 0.0, 1.0)),
+    ("pink", PINK),
+];
+
+// The CSS named colors, sorted by name
+const CSS_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
+
+// Reads a color the way the data files write them: `#RGB`, `#RRGGBB`,
+// `#RRGGBBAA`, `transparent` or a CSS color name, in any case. Loaders check
+// every color with this so a typo is an error instead of a white tile.
+pub fn parse_color(color_str: &str) -> Result<Color, String> {
+    if let Some(hex) = color_str.strip_prefix('#') {
+        return parse_hex(hex).ok_or_else(|| format!("Bad hex color {}", color_str));
+    }
+    let name = color_str.to_ascii_lowercase();
+    if name == "transparent" {
+        return Ok(Color::new(0.0, 0.0, 0.0, 0.0));
+    }
+    if let Some((_, color)) = GAME_COLORS.iter().find(|(game_name, _)| *game_name == name) {
+        return Ok(*color);
+    }
+    match CSS_COLORS.binary_search_by(|(css_name, _)| (*css_name).cmp(name.as_str())) {
+        Ok(i) => {
+            let [_, r, g, b] = CSS_COLORS[i].1.to_be_bytes();
+            Ok(Color::from_rgba(r, g, b, 255))
+        },
+        Err(_) => Err(format!("Unknown color {}", color_str)),
+    }
+}
+
+// RGB with one digit per channel, RGB or RGBA with two
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+    let (digits, channels) = match hex.len() {
+        3 => (1, 3),
+        6 => (2, 3),
+        8 => (2, 4),
+        _ => return None,
+    };
+    let mut rgba = [255u8; 4];
+    for (i, channel) in rgba.iter_mut().enumerate().take(channels) {
+        let value = u8::from_str_radix(&hex[i * digits..(i + 1) * digits], 16).ok()?;
+        // #F00 is #FF0000
+        *channel = if digits == 1 { value * 17 } else { value };
+    }
+    Some(Color::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color::from_rgba(r, g, b, a)
+    }
+
+    #[test]
+    fn hex_forms() {
+        assert_eq!(parse_color("#8B7355"), Ok(rgba(0x8B, 0x73, 0x55, 255)));
+        assert_eq!(parse_color("#f0a"), Ok(rgba(0xFF, 0x00, 0xAA, 255)));
+        assert_eq!(parse_color("#FF000080"), Ok(rgba(255, 0, 0, 128)));
+        assert_eq!(parse_color("#00000000"), Ok(rgba(0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn bad_hex_is_an_error() {
+        for bad in ["#", "#12", "#1234", "#12345", "#1234567", "#123456789", "#GGG", "#12345Z", "#+F0000", "#ééé"] {
+            assert!(parse_color(bad).is_err(), "{} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(parse_color("transparent"), Ok(Color::new(0.0, 0.0, 0.0, 0.0)));
+        assert_eq!(parse_color("rebeccapurple"), Ok(rgba(0x66, 0x33, 0x99, 255)));
+        assert_eq!(parse_color("AliceBlue"), Ok(rgba(0xF0, 0xF8, 0xFF, 255)));
+        assert_eq!(parse_color("yellowgreen"), Ok(rgba(0x9A, 0xCD, 0x32, 255)));
+        assert!(CSS_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0), "CSS_COLORS must stay sorted");
+    }
+
+    #[test]
+    fn only_game_colors_skip_css() {
+        let game: Vec<&str> = GAME_COLORS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(game, ["green", "darkgreen", "gray", "darkgray", "brown", "pink"]);
+        assert_eq!(parse_color("green"), Ok(GREEN));
+        assert_eq!(parse_color("DarkGreen"), Ok(Color::new(0.0, 0.5, 0.0, 1.0)));
+        assert_eq!(parse_color("gray"), Ok(GRAY));
+        assert_eq!(parse_color("darkgray"), Ok(DARKGRAY));
+        assert_eq!(parse_color("brown"), Ok(Color::new(0.5, 0.25, 0.0, 1.0)));
+        assert_eq!(parse_color("pink"), Ok(PINK));
+
+        // Names macroquad also has a constant for still give CSS's value
+        assert_eq!(parse_color("red"), Ok(rgba(0xFF, 0x00, 0x00, 255)));
+        assert_eq!(parse_color("blue"), Ok(rgba(0x00, 0x00, 0xFF, 255)));
+        assert_eq!(parse_color("purple"), Ok(rgba(0x80, 0x00, 0x80, 255)));
+        assert_eq!(parse_color("yellow"), Ok(rgba(0xFF, 0xFF, 0x00, 255)));
+        assert_eq!(parse_color("orange"), Ok(rgba(0xFF, 0xA5, 0x00, 255)));
+        assert_eq!(parse_color("lightgray"), Ok(rgba(0xD3, 0xD3, 0xD3, 255)));
+        assert_eq!(parse_color("darkblue"), Ok(rgba(0x00, 0x00, 0x8B, 255)));
+    }
+
+    #[test]
+    fn unknown_names_are_an_error() {
+        for bad in ["", "grene", "light gray", "red;"] {
+            assert_eq!(parse_color(bad), Err(format!("Unknown color {}", bad)));
+        }
+    }
+}
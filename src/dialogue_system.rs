@@ -0,0 +1,376 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use crate::biome_system::ItemConfig;
+use crate::sprite_system::{Facing, SpriteRenderer};
+
+// A friendly character as placed in map YAML; x/y are tile coordinates like monster spawns
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NpcData {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub dialogue: String,
+    #[serde(default = "default_npc_sprite")]
+    pub sprite: String,
+}
+
+fn default_npc_sprite() -> String {
+    "villager".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DialogueTree {
+    // The first entry whose conditions pass picks the opening node
+    pub start: Vec<DialogueBranch>,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DialogueBranch {
+    pub next: String,
+    #[serde(default)]
+    pub conditions: Vec<DialogueCondition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DialogueNode {
+    #[serde(default)]
+    pub speaker: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    pub actions: Vec<DialogueAction>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DialogueChoice {
+    pub text: String,
+    #[serde(default)]
+    pub conditions: Vec<DialogueCondition>,
+    // No next node ends the conversation
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogueCondition {
+    FlagSet(String),
+    FlagNotSet(String),
+    HasItem(String),
+    MinGold(i32),
+    MinLevel(i32),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogueAction {
+    GiveItem(String),
+    TakeItem(String),
+    GiveGold(i32),
+    TakeGold(i32),
+    SetFlag(String),
+    ClearFlag(String),
+}
+
+// The slice of game state dialogue is allowed to read and change
+pub struct DialogueContext<'a> {
+    pub level: i32,
+    pub gold: &'a mut i32,
+    pub inventory: &'a mut Vec<String>,
+    pub flags: &'a mut HashSet<String>,
+}
+
+impl DialogueCondition {
+    pub fn is_met(&self, ctx: &DialogueContext) -> bool {
+        match self {
+            DialogueCondition::FlagSet(flag) => ctx.flags.contains(flag),
+            DialogueCondition::FlagNotSet(flag) => !ctx.flags.contains(flag),
+            DialogueCondition::HasItem(item) => ctx.inventory.contains(item),
+            DialogueCondition::MinGold(gold) => *ctx.gold >= *gold,
+            DialogueCondition::MinLevel(level) => ctx.level >= *level,
+        }
+    }
+}
+
+impl DialogueAction {
+    // Returns a short notice for the player, if the action is worth announcing
+    pub fn apply(&self, ctx: &mut DialogueContext, items: &HashMap<String, ItemConfig>) -> Option<String> {
+        let item_name = |id: &String| items.get(id).map(|item| item.name.clone()).unwrap_or_else(|| id.clone());
+
+        match self {
+            DialogueAction::GiveItem(item) => {
+                ctx.inventory.push(item.clone());
+                Some(format!("Received {}", item_name(item)))
+            },
+            DialogueAction::TakeItem(item) => {
+                let index = ctx.inventory.iter().position(|id| id == item)?;
+                ctx.inventory.remove(index);
+                Some(format!("Handed over {}", item_name(item)))
+            },
+            DialogueAction::GiveGold(gold) => {
+                *ctx.gold += gold;
+                Some(format!("Received {} gold", gold))
+            },
+            DialogueAction::TakeGold(gold) => {
+                *ctx.gold = (*ctx.gold - gold).max(0);
+                Some(format!("Paid {} gold", gold))
+            },
+            DialogueAction::SetFlag(flag) => {
+                ctx.flags.insert(flag.clone());
+                None
+            },
+            DialogueAction::ClearFlag(flag) => {
+                ctx.flags.remove(flag);
+                None
+            },
+        }
+    }
+}
+
+fn all_met(conditions: &[DialogueCondition], ctx: &DialogueContext) -> bool {
+    conditions.iter().all(|condition| condition.is_met(ctx))
+}
+
+pub fn load_dialogue(path: &str) -> Result<DialogueTree, String> {
+    let tree = match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            match serde_yaml::from_str::<DialogueTree>(&contents) {
+                Ok(tree) => tree,
+                Err(e) => return Err(format!("Dialogue YAML parsing error: {}", e)),
+            }
+        },
+        Err(e) => return Err(format!("Dialogue file read error: {}", e)),
+    };
+
+    // Catch dangling node references at load time rather than mid-conversation
+    let references = tree.start.iter().map(|branch| &branch.next)
+        .chain(tree.nodes.values().flat_map(|node| {
+            node.next.iter().chain(node.choices.iter().filter_map(|choice| choice.next.as_ref()))
+        }));
+    for node_id in references {
+        if !tree.nodes.contains_key(node_id) {
+            return Err(format!("Dialogue {} refers to unknown node '{}'", path, node_id));
+        }
+    }
+
+    Ok(tree)
+}
+
+const TYPEWRITER_SPEED: f32 = 40.0; // characters per second
+
+struct ActiveDialogue {
+    tree: DialogueTree,
+    npc_name: String,
+    node_id: String,
+    // Choices of the current node whose conditions passed when it was entered
+    choices: Vec<DialogueChoice>,
+    chars_shown: f32,
+    selected: usize,
+}
+
+pub struct DialogueBox {
+    active: Option<ActiveDialogue>,
+    cache: HashMap<String, DialogueTree>,
+    notices: Vec<(String, f32)>,
+}
+
+impl DialogueBox {
+    pub fn new() -> Self {
+        DialogueBox {
+            active: None,
+            cache: HashMap::new(),
+            notices: Vec::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn start(&mut self, npc: &NpcData, ctx: &mut DialogueContext, items: &HashMap<String, ItemConfig>) {
+        if !self.cache.contains_key(&npc.dialogue) {
+            match load_dialogue(&npc.dialogue) {
+                Ok(tree) => {
+                    self.cache.insert(npc.dialogue.clone(), tree);
+                },
+                Err(e) => {
+                    println!("Failed to load dialogue for {}: {}", npc.name, e);
+                    return;
+                },
+            }
+        }
+
+        let tree = self.cache[&npc.dialogue].clone();
+        let start = tree.start.iter().find(|branch| all_met(&branch.conditions, ctx)).map(|branch| branch.next.clone());
+        if let Some(node_id) = start {
+            self.active = Some(ActiveDialogue {
+                tree,
+                npc_name: npc.name.clone(),
+                node_id: String::new(),
+                choices: Vec::new(),
+                chars_shown: 0.0,
+                selected: 0,
+            });
+            self.enter_node(&node_id, ctx, items);
+        }
+    }
+
+    fn enter_node(&mut self, node_id: &str, ctx: &mut DialogueContext, items: &HashMap<String, ItemConfig>) {
+        let dialogue = match &mut self.active {
+            Some(dialogue) => dialogue,
+            None => return,
+        };
+
+        dialogue.node_id = node_id.to_string();
+        dialogue.chars_shown = 0.0;
+        dialogue.selected = 0;
+
+        let node = &dialogue.tree.nodes[node_id];
+        for action in &node.actions {
+            if let Some(notice) = action.apply(ctx, items) {
+                self.notices.push((notice, 2.5));
+            }
+        }
+
+        // Conditions are checked after the node's own actions have run
+        dialogue.choices = node.choices.iter()
+            .filter(|choice| all_met(&choice.conditions, ctx))
+            .cloned()
+            .collect();
+    }
+
+    pub fn update(&mut self, delta: f32, ctx: &mut DialogueContext, items: &HashMap<String, ItemConfig>) {
+        self.notices.retain_mut(|(_, timer)| {
+            *timer -= delta;
+            *timer > 0.0
+        });
+
+        let dialogue = match &mut self.active {
+            Some(dialogue) => dialogue,
+            None => return,
+        };
+
+        let node = &dialogue.tree.nodes[&dialogue.node_id];
+        let text_len = node.text.chars().count() as f32;
+        let confirm = is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::E);
+
+        if dialogue.chars_shown < text_len {
+            dialogue.chars_shown += delta * TYPEWRITER_SPEED;
+            if confirm {
+                // Skip the typewriter effect
+                dialogue.chars_shown = text_len;
+            }
+            return;
+        }
+
+        if is_key_pressed(KeyCode::Up) && dialogue.selected > 0 {
+            dialogue.selected -= 1;
+        }
+        if is_key_pressed(KeyCode::Down) && dialogue.selected + 1 < dialogue.choices.len() {
+            dialogue.selected += 1;
+        }
+
+        if !confirm {
+            return;
+        }
+
+        let next = match dialogue.choices.get(dialogue.selected) {
+            Some(choice) => choice.next.clone(),
+            None => node.next.clone(),
+        };
+
+        match next {
+            Some(node_id) => self.enter_node(&node_id, ctx, items),
+            None => self.active = None,
+        }
+    }
+
+    pub fn draw(&self) {
+        for (i, (notice, _)) in self.notices.iter().enumerate() {
+            let width = measure_text(notice, None, 20, 1.0).width;
+            draw_text(notice, screen_width() / 2.0 - width / 2.0, 120.0 + i as f32 * 24.0, 20.0, SKYBLUE);
+        }
+
+        let dialogue = match &self.active {
+            Some(dialogue) => dialogue,
+            None => return,
+        };
+        let node = &dialogue.tree.nodes[&dialogue.node_id];
+
+        let box_height = 170.0;
+        let box_x = 40.0;
+        let box_y = screen_height() - box_height - 20.0;
+        let box_width = screen_width() - 80.0;
+
+        draw_rectangle(box_x, box_y, box_width, box_height, Color::new(0.0, 0.0, 0.1, 0.9));
+        draw_rectangle_lines(box_x, box_y, box_width, box_height, 2.0, WHITE);
+
+        let speaker = node.speaker.as_deref().unwrap_or(&dialogue.npc_name);
+        draw_text(speaker, box_x + 15.0, box_y + 28.0, 22.0, YELLOW);
+
+        // Typewriter: reveal the text a character at a time, wrapping on words
+        let shown: String = node.text.chars().take(dialogue.chars_shown as usize).collect();
+        let max_width = box_width - 30.0;
+        let mut y = box_y + 56.0;
+        let mut line = String::new();
+        for word in shown.split(' ') {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if measure_text(&candidate, None, 20, 1.0).width > max_width && !line.is_empty() {
+                draw_text(&line, box_x + 15.0, y, 20.0, WHITE);
+                y += 24.0;
+                line = word.to_string();
+            } else {
+                line = candidate;
+            }
+        }
+        draw_text(&line, box_x + 15.0, y, 20.0, WHITE);
+
+        if (dialogue.chars_shown as usize) < node.text.chars().count() {
+            return;
+        }
+
+        let choices = &dialogue.choices;
+        if choices.is_empty() {
+            let hint = if node.next.is_some() { "[ENTER] Continue" } else { "[ENTER] Close" };
+            draw_text(hint, box_x + box_width - 160.0, box_y + box_height - 12.0, 16.0, GRAY);
+        } else {
+            let mut choice_y = box_y + box_height - 12.0 - (choices.len() - 1) as f32 * 22.0;
+            for (i, choice) in choices.iter().enumerate() {
+                let selected = i == dialogue.selected;
+                let marker = if selected { "> " } else { "  " };
+                draw_text(&format!("{}{}", marker, choice.text), box_x + 30.0, choice_y, 18.0, if selected { YELLOW } else { LIGHTGRAY });
+                choice_y += 22.0;
+            }
+        }
+    }
+}
+
+pub fn draw_npc(
+    sprite_renderer: &SpriteRenderer,
+    npc: &NpcData,
+    screen_pos: Vec2,
+    player_nearby: bool,
+) {
+    if sprite_renderer.has_frame(&npc.sprite, "idle") {
+        sprite_renderer.draw_sprite_outlined(&npc.sprite, "idle", screen_pos, 2.0, Facing::Down.into(), BLACK);
+    } else {
+        draw_circle(screen_pos.x, screen_pos.y, 12.0, SKYBLUE);
+    }
+
+    // Labels go just above and below the sprite, whatever its size
+    let bounds = sprite_renderer.bounds(&npc.sprite, screen_pos, 2.0)
+        .unwrap_or(Rect::new(screen_pos.x - 16.0, screen_pos.y - 16.0, 32.0, 32.0));
+    let name_width = measure_text(&npc.name, None, 16, 1.0).width;
+    draw_text(&npc.name, screen_pos.x - name_width / 2.0, bounds.y - 6.0, 16.0, SKYBLUE);
+
+    if player_nearby {
+        let prompt = "[E] Talk";
+        let prompt_width = measure_text(prompt, None, 16, 1.0).width;
+        draw_text(prompt, screen_pos.x - prompt_width / 2.0, bounds.bottom() + 18.0, 16.0, WHITE);
+    }
+}
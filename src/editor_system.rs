@@ -0,0 +1,609 @@
+use macroquad::prelude::*;
+use std::collections::{HashMap, HashSet};
+use crate::color_system::parse_color;
+use crate::sheet_system::save_sprites;
+use crate::sprite_system::{Animator, CharacterSprite, Facing, SpriteData, SpriteRenderer, load_sprites};
+
+// Layout: sprite list, frame list, the canvas, then palette and preview
+// along the right edge. The canvas takes whatever room is left, so the
+// editor fits the game's window as well as its own.
+const SPRITE_LIST_X: f32 = 10.0;
+const FRAME_LIST_X: f32 = 190.0;
+const LIST_WIDTH: f32 = 170.0;
+const LIST_TOP: f32 = 60.0;
+const LINE_HEIGHT: f32 = 20.0;
+const CANVAS_X: f32 = 370.0;
+const CANVAS_Y: f32 = 60.0;
+const MIN_CANVAS_SIZE: f32 = 64.0;
+const PANEL_MARGIN: f32 = 14.0;
+const PALETTE_Y: f32 = 80.0;
+const SWATCH_SIZE: f32 = 22.0;
+const SWATCH_GAP: f32 = 3.0;
+const SWATCH_COLUMNS: usize = 8;
+const PREVIEW_SIZE: f32 = 200.0;
+const PREVIEW_SCALE: f32 = 4.0;
+// How strongly the frame before shows through with onion skinning on
+const ONION_ALPHA: f32 = 0.3;
+const UNDO_LIMIT: usize = 200;
+
+const FACINGS: [Facing; 8] = [
+    Facing::Down, Facing::DownRight, Facing::Right, Facing::UpRight,
+    Facing::Up, Facing::UpLeft, Facing::Left, Facing::DownLeft,
+];
+
+// `--edit-sprites <sprites.json>` on the command line
+pub fn editor_args() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let i = args.iter().position(|arg| arg == "--edit-sprites")?;
+    args.get(i + 1).cloned()
+}
+
+pub fn window_conf() -> Conf {
+    Conf {
+        window_title: "Sprite Editor".to_string(),
+        window_width: 1024,
+        window_height: 640,
+        ..Default::default()
+    }
+}
+
+pub async fn run_sprite_editor(path: String) {
+    let data = match load_sprites(&path) {
+        Ok(data) => data,
+        Err(e) => {
+            println!("Failed to load sprites: {}", e);
+            return;
+        },
+    };
+    let mut editor = match SpriteEditor::new(path, data) {
+        Some(editor) => editor,
+        None => {
+            println!("No sprites to edit: sprites kept as PNG sheets are edited in an image editor");
+            return;
+        },
+    };
+
+    loop {
+        if editor.update() == EditorEvent::Closed {
+            return;
+        }
+        editor.draw();
+        next_frame().await;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditorEvent {
+    Editing,
+    // The file was written; renderers built from it are out of date
+    Saved,
+    Closed,
+}
+
+// Edits the ASCII frames of one sprite file and saves them back in place
+pub struct SpriteEditor {
+    path: String,
+    data: SpriteData,
+    // Sprites written out as ASCII in the file; ones read from PNG sheets
+    // would be lost on saving, so they aren't listed
+    sprite_names: Vec<String>,
+    sprite: usize,
+    frame: usize,
+    color: char,
+    onion_skin: bool,
+    // 0 shows the frame being edited, then the sprite's animations by name
+    preview: usize,
+    facing: Facing,
+    animator: Animator,
+    // Bakes only the sprite being edited, again after every change
+    renderer: SpriteRenderer,
+    renderer_stale: bool,
+    // Sprite, frame and rows from before each stroke
+    undo: Vec<(String, String, Vec<String>)>,
+    painting: bool,
+    edited: HashSet<String>,
+    status: String,
+    quit_warned: bool,
+}
+
+impl SpriteEditor {
+    pub fn new(path: String, data: SpriteData) -> Option<Self> {
+        let mut sprite_names: Vec<String> = data.sprites.keys()
+            .filter(|name| !data.sheets.contains_key(*name))
+            .cloned()
+            .collect();
+        sprite_names.sort();
+        let first = sprite_names.first()?;
+        let renderer = SpriteRenderer::new(single_sprite(&data, first));
+        Some(SpriteEditor {
+            path,
+            data,
+            sprite_names,
+            sprite: 0,
+            frame: 0,
+            color: '.',
+            onion_skin: false,
+            preview: 0,
+            facing: Facing::Down,
+            animator: Animator::new(),
+            renderer,
+            renderer_stale: false,
+            undo: Vec::new(),
+            painting: false,
+            edited: HashSet::new(),
+            status: String::new(),
+            quit_warned: false,
+        })
+    }
+
+    // The sprites as edited, matching the file since the last save
+    pub fn data(&self) -> &SpriteData {
+        &self.data
+    }
+
+    fn sprite_name(&self) -> &str {
+        &self.sprite_names[self.sprite]
+    }
+
+    fn current(&self) -> &CharacterSprite {
+        &self.data.sprites[self.sprite_name()]
+    }
+
+    // Plain frames, then each direction's as `<direction>/<frame>`, both in
+    // name order like exported sheets
+    fn frame_keys(&self) -> Vec<String> {
+        let sprite = self.current();
+        let mut keys: Vec<String> = sprite.frames.keys().cloned().collect();
+        keys.sort();
+        let mut directional: Vec<String> = sprite.directions.iter()
+            .flat_map(|(direction, frames)| frames.keys().map(move |frame_name| format!("{}/{}", direction, frame_name)))
+            .collect();
+        directional.sort();
+        keys.extend(directional);
+        keys
+    }
+
+    fn frame_key(&self) -> String {
+        self.frame_keys().get(self.frame).cloned().unwrap_or_default()
+    }
+
+    fn animation_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.current().animations.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn preview_animation(&self) -> Option<String> {
+        self.animation_names().get(self.preview.checked_sub(1)?).cloned()
+    }
+
+    // The shared palette with this sprite's own colors over it
+    fn colors(&self) -> HashMap<char, Color> {
+        self.data.color_palette.iter()
+            .chain(&self.current().palette)
+            .filter_map(|(key, color)| Some((key.chars().next()?, parse_color(color).ok()?)))
+            .collect()
+    }
+
+    // Transparent first, then the palette's keys in order
+    fn palette_keys(&self) -> Vec<char> {
+        let mut keys: Vec<char> = self.colors().into_keys().filter(|&key| key != '.').collect();
+        keys.sort();
+        keys.insert(0, '.');
+        keys
+    }
+
+    fn select_sprite(&mut self, sprite: usize) {
+        self.sprite = sprite;
+        self.preview = 0;
+        self.animator = Animator::new();
+        self.renderer_stale = true;
+        self.select_frame(0);
+    }
+
+    fn select_frame(&mut self, frame: usize) {
+        self.frame = frame;
+        self.facing = facing_for(&self.frame_key());
+    }
+
+    fn select_preview(&mut self, preview: usize) {
+        self.preview = preview;
+        if let Some(name) = self.preview_animation() {
+            self.animator.play_once(&name);
+        }
+    }
+
+    // The frame drawn faintly under the one being edited: the one before it
+    // in the animation being previewed, else the one before it in the list
+    fn onion_frame(&self) -> Option<String> {
+        let keys = self.frame_keys();
+        let key = keys.get(self.frame)?;
+        let (direction, frame_name) = match key.split_once('/') {
+            Some((direction, frame_name)) => (Some(direction), frame_name),
+            None => (None, key.as_str()),
+        };
+        let animation = self.preview_animation().and_then(|name| self.current().animations.get(&name));
+        if let Some(frames) = animation.map(|animation| &animation.frames) {
+            if let Some(i) = frames.iter().position(|frame| frame.frame == frame_name) {
+                let previous = &frames[(i + frames.len() - 1) % frames.len()].frame;
+                let in_direction = direction.map(|direction| format!("{}/{}", direction, previous));
+                return in_direction.into_iter()
+                    .chain([previous.clone()])
+                    .find(|candidate| keys.contains(candidate) && candidate != key);
+            }
+        }
+        if keys.len() < 2 {
+            return None;
+        }
+        Some(keys[(self.frame + keys.len() - 1) % keys.len()].clone())
+    }
+
+    fn paint(&mut self, x: usize, y: usize) {
+        let name = self.sprite_name().to_string();
+        let key = self.frame_key();
+        let color = self.color;
+        let Some(rows) = self.data.sprites.get_mut(&name).and_then(|sprite| frame_rows_mut(sprite, &key)) else { return };
+        if rows[y].chars().nth(x) == Some(color) {
+            return;
+        }
+        if !self.painting {
+            self.undo.push((name.clone(), key, rows.clone()));
+            if self.undo.len() > UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+            self.painting = true;
+        }
+        rows[y] = rows[y].chars()
+            .enumerate()
+            .map(|(i, old)| if i == x { color } else { old })
+            .collect();
+        self.edited.insert(name);
+        self.renderer_stale = true;
+    }
+
+    fn undo(&mut self) {
+        let Some((name, key, old_rows)) = self.undo.pop() else {
+            self.status = "Nothing to undo".to_string();
+            return;
+        };
+        if let Some(rows) = self.data.sprites.get_mut(&name).and_then(|sprite| frame_rows_mut(sprite, &key)) {
+            *rows = old_rows;
+        }
+        if let Some(sprite) = self.sprite_names.iter().position(|sprite_name| *sprite_name == name) {
+            if sprite != self.sprite {
+                self.select_sprite(sprite);
+            }
+            if let Some(frame) = self.frame_keys().iter().position(|frame_key| *frame_key == key) {
+                self.select_frame(frame);
+            }
+        }
+        self.edited.insert(name);
+        self.renderer_stale = true;
+    }
+
+    fn save(&mut self) -> bool {
+        let mut names: Vec<&String> = self.edited.iter().collect();
+        names.sort();
+        let sprites: Vec<(&str, &CharacterSprite)> = names.iter()
+            .map(|name| (name.as_str(), &self.data.sprites[*name]))
+            .collect();
+        match save_sprites(&self.path, &sprites) {
+            Ok(()) => {
+                self.status = format!("Saved {} sprites to {}", sprites.len(), self.path);
+                self.edited.clear();
+                true
+            },
+            Err(e) => {
+                self.status = e;
+                false
+            },
+        }
+    }
+
+    // Handles this frame's keys and clicks
+    pub fn update(&mut self) -> EditorEvent {
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if is_key_pressed(KeyCode::Escape) {
+            if self.edited.is_empty() || self.quit_warned {
+                return EditorEvent::Closed;
+            }
+            self.quit_warned = true;
+            self.status = "Unsaved changes: Ctrl+S saves, Esc again closes without saving".to_string();
+            return EditorEvent::Editing;
+        }
+        if get_last_key_pressed().is_some() {
+            self.quit_warned = false;
+        }
+
+        let sprite_count = self.sprite_names.len();
+        let frame_count = self.frame_keys().len().max(1);
+        let mut event = EditorEvent::Editing;
+        if ctrl && is_key_pressed(KeyCode::S) && self.save() {
+            event = EditorEvent::Saved;
+        }
+        if ctrl && is_key_pressed(KeyCode::Z) {
+            self.undo();
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.select_sprite((self.sprite + sprite_count - 1) % sprite_count);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.select_sprite((self.sprite + 1) % sprite_count);
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.select_frame((self.frame + frame_count - 1) % frame_count);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.select_frame((self.frame + 1) % frame_count);
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            self.select_preview((self.preview + 1) % (self.animation_names().len() + 1));
+        }
+        if is_key_pressed(KeyCode::F) {
+            let i = FACINGS.iter().position(|&facing| facing == self.facing).unwrap_or(0);
+            self.facing = FACINGS[(i + 1) % FACINGS.len()];
+        }
+        if is_key_pressed(KeyCode::O) && !ctrl {
+            self.onion_skin = !self.onion_skin;
+        }
+
+        let (mouse_x, mouse_y) = mouse_position();
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let row = ((mouse_y - LIST_TOP) / LINE_HEIGHT).floor();
+            if row >= 0.0 && (SPRITE_LIST_X..SPRITE_LIST_X + LIST_WIDTH).contains(&mouse_x) && (row as usize) < sprite_count {
+                self.select_sprite(row as usize);
+            }
+            if row >= 0.0 && (FRAME_LIST_X..FRAME_LIST_X + LIST_WIDTH).contains(&mouse_x) && (row as usize) < self.frame_keys().len() {
+                self.select_frame(row as usize);
+            }
+            let palette = self.palette_keys();
+            if let Some(&color) = swatch_at(mouse_x, mouse_y).and_then(|i| palette.get(i)) {
+                self.color = color;
+            }
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.painting = false;
+        }
+        if let Some((x, y)) = self.pixel_at(mouse_x, mouse_y) {
+            if is_mouse_button_down(MouseButton::Left) {
+                self.paint(x, y);
+            }
+            if is_mouse_button_pressed(MouseButton::Right) {
+                let picked = frame_rows(self.current(), &self.frame_key()).and_then(|rows| rows[y].chars().nth(x));
+                if let Some(color) = picked {
+                    self.color = color;
+                }
+            }
+        }
+
+        if self.renderer_stale {
+            self.renderer = SpriteRenderer::new(single_sprite(&self.data, self.sprite_name()));
+            self.renderer_stale = false;
+        }
+        if let Some(name) = self.preview_animation() {
+            self.animator.update(&self.renderer, &self.sprite_names[self.sprite], get_frame_time());
+            // Keep one-shot animations repeating too
+            if !self.animator.is_playing(&name) {
+                self.animator.play_once(&name);
+            }
+        }
+        event
+    }
+
+    // Screen pixels per sprite pixel on the canvas
+    fn cell_size(&self) -> f32 {
+        let (width, height) = self.current().size(&self.data.sprite_info);
+        (canvas_size() / width.max(height).max(1) as f32).floor().max(1.0)
+    }
+
+    fn pixel_at(&self, mouse_x: f32, mouse_y: f32) -> Option<(usize, usize)> {
+        let (width, height) = self.current().size(&self.data.sprite_info);
+        let cell = self.cell_size();
+        let x = ((mouse_x - CANVAS_X) / cell).floor();
+        let y = ((mouse_y - CANVAS_Y) / cell).floor();
+        if x < 0.0 || y < 0.0 || x as usize >= width || y as usize >= height {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    pub fn draw(&self) {
+        clear_background(Color::new(0.15, 0.15, 0.18, 1.0));
+        let unsaved = if self.edited.is_empty() { "" } else { " (unsaved)" };
+        draw_text(&format!("Sprite Editor - {}{}", self.path, unsaved), 10.0, 30.0, 24.0, WHITE);
+
+        let frame_keys = self.frame_keys();
+        draw_text("Sprites", SPRITE_LIST_X, LIST_TOP - 8.0, 18.0, GRAY);
+        draw_text("Frames", FRAME_LIST_X, LIST_TOP - 8.0, 18.0, GRAY);
+        for (i, name) in self.sprite_names.iter().enumerate() {
+            let label = if self.edited.contains(name) { format!("{}*", name) } else { name.clone() };
+            draw_list_row(SPRITE_LIST_X, i, &label, i == self.sprite);
+        }
+        for (i, key) in frame_keys.iter().enumerate() {
+            draw_list_row(FRAME_LIST_X, i, key, i == self.frame);
+        }
+
+        self.draw_canvas();
+        self.draw_palette();
+        self.draw_preview();
+
+        draw_text("Click paint  Right-click pick  Up/Down sprite  Left/Right frame  Tab animation", 10.0, screen_height() - 52.0, 16.0, LIGHTGRAY);
+        draw_text("F facing  O onion skin  Ctrl+Z undo  Ctrl+S save  Esc close", 10.0, screen_height() - 32.0, 16.0, LIGHTGRAY);
+        draw_text(&self.status, 10.0, screen_height() - 12.0, 18.0, YELLOW);
+    }
+
+    fn draw_canvas(&self) {
+        let sprite = self.current();
+        let (width, height) = sprite.size(&self.data.sprite_info);
+        let cell = self.cell_size();
+        let colors = self.colors();
+
+        // Checkerboard behind transparent pixels
+        for y in 0..height {
+            for x in 0..width {
+                let shade = if (x + y) % 2 == 0 { 0.35 } else { 0.45 };
+                draw_rectangle(CANVAS_X + x as f32 * cell, CANVAS_Y + y as f32 * cell, cell, cell, Color::new(shade, shade, shade, 1.0));
+            }
+        }
+        let mut layers = Vec::new();
+        if self.onion_skin {
+            if let Some(rows) = self.onion_frame().and_then(|key| frame_rows(sprite, &key)) {
+                layers.push((rows, ONION_ALPHA));
+            }
+        }
+        if let Some(rows) = frame_rows(sprite, &self.frame_key()) {
+            layers.push((rows, 1.0));
+        }
+        for (rows, alpha) in layers {
+            for (y, row) in rows.iter().enumerate() {
+                for (x, ch) in row.chars().enumerate() {
+                    if ch == '.' {
+                        continue;
+                    }
+                    let mut color = colors.get(&ch).copied().unwrap_or(WHITE);
+                    color.a *= alpha;
+                    draw_rectangle(CANVAS_X + x as f32 * cell, CANVAS_Y + y as f32 * cell, cell, cell, color);
+                }
+            }
+        }
+
+        let grid = Color::new(0.0, 0.0, 0.0, 0.25);
+        for x in 0..=width {
+            let line_x = CANVAS_X + x as f32 * cell;
+            draw_line(line_x, CANVAS_Y, line_x, CANVAS_Y + height as f32 * cell, 1.0, grid);
+        }
+        for y in 0..=height {
+            let line_y = CANVAS_Y + y as f32 * cell;
+            draw_line(CANVAS_X, line_y, CANVAS_X + width as f32 * cell, line_y, 1.0, grid);
+        }
+        let anchor = vec2(CANVAS_X, CANVAS_Y) + sprite.anchor_point(&self.data.sprite_info) * cell;
+        draw_line(anchor.x - 6.0, anchor.y, anchor.x + 6.0, anchor.y, 2.0, RED);
+        draw_line(anchor.x, anchor.y - 6.0, anchor.x, anchor.y + 6.0, 2.0, RED);
+
+        let onion = if self.onion_skin {
+            format!("  onion skin: {}", self.onion_frame().unwrap_or_else(|| "none".to_string()))
+        } else {
+            String::new()
+        };
+        let below = CANVAS_Y + height as f32 * cell + 22.0;
+        draw_text(&format!("{}x{}{}", width, height, onion), CANVAS_X, below, 18.0, LIGHTGRAY);
+    }
+
+    fn draw_palette(&self) {
+        let colors = self.colors();
+        let color_name = match self.color {
+            '.' => "transparent".to_string(),
+            key => self.current().palette.get(&key.to_string())
+                .or_else(|| self.data.color_palette.get(&key.to_string()))
+                .cloned()
+                .unwrap_or_default(),
+        };
+        draw_text(&format!("Color: {} {}", self.color, color_name), panel_x(), PALETTE_Y - 12.0, 18.0, WHITE);
+        for (i, key) in self.palette_keys().into_iter().enumerate() {
+            let (x, y) = swatch_position(i);
+            if key == '.' {
+                draw_rectangle(x, y, SWATCH_SIZE, SWATCH_SIZE, Color::new(0.35, 0.35, 0.35, 1.0));
+                draw_rectangle(x, y, SWATCH_SIZE / 2.0, SWATCH_SIZE / 2.0, Color::new(0.45, 0.45, 0.45, 1.0));
+                draw_rectangle(x + SWATCH_SIZE / 2.0, y + SWATCH_SIZE / 2.0, SWATCH_SIZE / 2.0, SWATCH_SIZE / 2.0, Color::new(0.45, 0.45, 0.45, 1.0));
+            } else {
+                draw_rectangle(x, y, SWATCH_SIZE, SWATCH_SIZE, colors.get(&key).copied().unwrap_or(WHITE));
+            }
+            if key == self.color {
+                draw_rectangle_lines(x - 2.0, y - 2.0, SWATCH_SIZE + 4.0, SWATCH_SIZE + 4.0, 2.0, YELLOW);
+            }
+        }
+    }
+
+    fn draw_preview(&self) {
+        let rows = self.palette_keys().len().div_ceil(SWATCH_COLUMNS);
+        let top = PALETTE_Y + rows as f32 * (SWATCH_SIZE + SWATCH_GAP) + 30.0;
+        let showing = self.preview_animation().unwrap_or_else(|| "frame".to_string());
+        let panel_x = panel_x();
+        draw_text(&format!("Preview: {}, facing {}", showing, self.facing.name()), panel_x, top, 18.0, WHITE);
+        draw_rectangle(panel_x, top + 10.0, PREVIEW_SIZE, PREVIEW_SIZE, DARKGREEN);
+
+        let frame_name = match self.preview_animation() {
+            Some(_) => self.animator.frame(&self.renderer, self.sprite_name()).to_string(),
+            None => {
+                let key = self.frame_key();
+                key.split_once('/').map(|(_, frame_name)| frame_name.to_string()).unwrap_or(key)
+            },
+        };
+        let center = vec2(panel_x + PREVIEW_SIZE / 2.0, top + 10.0 + PREVIEW_SIZE / 2.0);
+        self.renderer.draw_sprite_outlined(self.sprite_name(), &frame_name, center, PREVIEW_SCALE, self.facing.into(), BLACK);
+    }
+}
+
+// The file's palette and sizes with just one of its sprites, for baking
+fn single_sprite(data: &SpriteData, name: &str) -> SpriteData {
+    SpriteData {
+        sprite_info: data.sprite_info.clone(),
+        color_palette: data.color_palette.clone(),
+        sprites: data.sprites.get(name).map(|sprite| (name.to_string(), sprite.clone())).into_iter().collect(),
+        sheets: HashMap::new(),
+    }
+}
+
+fn frame_rows<'a>(sprite: &'a CharacterSprite, key: &str) -> Option<&'a Vec<String>> {
+    match key.split_once('/') {
+        Some((direction, frame_name)) => sprite.directions.get(direction)?.get(frame_name),
+        None => sprite.frames.get(key),
+    }
+}
+
+fn frame_rows_mut<'a>(sprite: &'a mut CharacterSprite, key: &str) -> Option<&'a mut Vec<String>> {
+    match key.split_once('/') {
+        Some((direction, frame_name)) => sprite.directions.get_mut(direction)?.get_mut(frame_name),
+        None => sprite.frames.get_mut(key),
+    }
+}
+
+// The way the preview faces for a frame: its direction, `side` facing
+// right, and plain frames facing down as entities start out
+fn facing_for(frame_key: &str) -> Facing {
+    match frame_key.split_once('/') {
+        Some(("side", _)) => Facing::Right,
+        Some((direction, _)) => FACINGS.into_iter().find(|facing| facing.name() == direction).unwrap_or_default(),
+        None => Facing::Down,
+    }
+}
+
+fn draw_list_row(x: f32, row: usize, label: &str, selected: bool) {
+    let y = LIST_TOP + row as f32 * LINE_HEIGHT;
+    if selected {
+        draw_rectangle(x - 4.0, y, LIST_WIDTH, LINE_HEIGHT, Color::new(0.3, 0.3, 0.5, 1.0));
+    }
+    draw_text(label, x, y + 15.0, 18.0, if selected { YELLOW } else { WHITE });
+}
+
+// Left edge of the palette and preview
+fn panel_x() -> f32 {
+    screen_width() - PREVIEW_SIZE - PANEL_MARGIN
+}
+
+fn canvas_size() -> f32 {
+    let across = panel_x() - PANEL_MARGIN - CANVAS_X;
+    // Room below for the size line, help and status
+    let down = screen_height() - CANVAS_Y - 80.0;
+    across.min(down).max(MIN_CANVAS_SIZE)
+}
+
+fn swatch_position(i: usize) -> (f32, f32) {
+    (
+        panel_x() + (i % SWATCH_COLUMNS) as f32 * (SWATCH_SIZE + SWATCH_GAP),
+        PALETTE_Y + (i / SWATCH_COLUMNS) as f32 * (SWATCH_SIZE + SWATCH_GAP),
+    )
+}
+
+fn swatch_at(mouse_x: f32, mouse_y: f32) -> Option<usize> {
+    let column = ((mouse_x - panel_x()) / (SWATCH_SIZE + SWATCH_GAP)).floor();
+    let row = ((mouse_y - PALETTE_Y) / (SWATCH_SIZE + SWATCH_GAP)).floor();
+    if column < 0.0 || row < 0.0 || column as usize >= SWATCH_COLUMNS {
+        return None;
+    }
+    let (x, y) = swatch_position(row as usize * SWATCH_COLUMNS + column as usize);
+    if mouse_x > x + SWATCH_SIZE || mouse_y > y + SWATCH_SIZE {
+        return None;
+    }
+    Some(row as usize * SWATCH_COLUMNS + column as usize)
+}
@@ -0,0 +1,258 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::biome_system::{ItemConfig, PlayerConfig};
+use crate::sprite_system::{Heading, SpriteRenderer};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armor,
+    Accessory,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 3] = [EquipmentSlot::Weapon, EquipmentSlot::Armor, EquipmentSlot::Accessory];
+
+    // Gear is layered body first so weapons and trinkets stay visible
+    pub const DRAW_ORDER: [EquipmentSlot; 3] = [EquipmentSlot::Armor, EquipmentSlot::Weapon, EquipmentSlot::Accessory];
+
+    pub fn for_item_type(item_type: &str) -> Option<EquipmentSlot> {
+        match item_type {
+            "weapon" => Some(EquipmentSlot::Weapon),
+            "armor" => Some(EquipmentSlot::Armor),
+            "accessory" => Some(EquipmentSlot::Accessory),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EquipmentSlot::Weapon => "Weapon",
+            EquipmentSlot::Armor => "Armor",
+            EquipmentSlot::Accessory => "Accessory",
+        }
+    }
+}
+
+// Item ids (keys of GameConfig.items) currently worn by the player
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Equipment {
+    pub weapon: Option<String>,
+    pub armor: Option<String>,
+    pub accessory: Option<String>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipmentSlot) -> Option<&String> {
+        match slot {
+            EquipmentSlot::Weapon => self.weapon.as_ref(),
+            EquipmentSlot::Armor => self.armor.as_ref(),
+            EquipmentSlot::Accessory => self.accessory.as_ref(),
+        }
+    }
+
+    fn slot_mut(&mut self, slot: EquipmentSlot) -> &mut Option<String> {
+        match slot {
+            EquipmentSlot::Weapon => &mut self.weapon,
+            EquipmentSlot::Armor => &mut self.armor,
+            EquipmentSlot::Accessory => &mut self.accessory,
+        }
+    }
+
+    // Swaps the inventory item into its slot; returns false for non-equippable items
+    pub fn equip_from_inventory(
+        &mut self,
+        inventory: &mut Vec<String>,
+        index: usize,
+        items: &HashMap<String, ItemConfig>,
+    ) -> bool {
+        let slot = match inventory.get(index)
+            .and_then(|id| items.get(id))
+            .and_then(|item| EquipmentSlot::for_item_type(&item.item_type))
+        {
+            Some(slot) => slot,
+            None => return false,
+        };
+
+        let item_id = inventory.remove(index);
+        if let Some(previous) = self.slot_mut(slot).replace(item_id) {
+            inventory.push(previous);
+        }
+        true
+    }
+
+    pub fn unequip_to_inventory(&mut self, slot: EquipmentSlot, inventory: &mut Vec<String>) -> bool {
+        match self.slot_mut(slot).take() {
+            Some(item_id) => {
+                inventory.push(item_id);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn equipped_items(&self) -> impl Iterator<Item = &String> {
+        EquipmentSlot::ALL.into_iter().filter_map(move |slot| self.get(slot))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerStats {
+    pub max_hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+}
+
+// Effective stats = base config + level bonuses + equipment bonuses
+pub fn calculate_stats(
+    config: &PlayerConfig,
+    level: i32,
+    equipment: &Equipment,
+    items: &HashMap<String, ItemConfig>,
+) -> PlayerStats {
+    let levels_gained = (level - 1).max(0);
+    let mut stats = PlayerStats {
+        max_hp: config.starting_hp + levels_gained * config.level_up_hp_bonus,
+        attack: config.starting_attack + levels_gained * config.level_up_attack_bonus,
+        defense: 0,
+    };
+
+    for item in equipment.equipped_items().filter_map(|id| items.get(id)) {
+        stats.attack += item.attack_bonus.unwrap_or(0);
+        stats.defense += item.defense_bonus.unwrap_or(0);
+    }
+
+    stats
+}
+
+pub fn contact_damage(monster_attack: i32, defense: i32) -> i32 {
+    (monster_attack - defense).max(1)
+}
+
+pub fn draw_equipment_overlay(
+    sprite_renderer: &SpriteRenderer,
+    equipment: &Equipment,
+    items: &HashMap<String, ItemConfig>,
+    frame_name: &str,
+    position: Vec2,
+    scale: f32,
+    heading: Heading,
+) {
+    for slot in EquipmentSlot::DRAW_ORDER {
+        let sprite_name = match equipment.get(slot)
+            .and_then(|id| items.get(id))
+            .and_then(|item| item.sprite.as_deref())
+        {
+            Some(name) => name,
+            None => continue,
+        };
+
+        // Gear only needs an idle frame; reuse it for any frame it doesn't define
+        let frame = if sprite_renderer.has_frame(sprite_name, frame_name) { frame_name } else { "idle" };
+        sprite_renderer.draw_sprite(sprite_name, frame, position, scale, heading);
+    }
+}
+
+pub struct InventoryPanel {
+    pub is_open: bool,
+    selected: usize,
+}
+
+impl InventoryPanel {
+    pub fn new() -> Self {
+        InventoryPanel {
+            is_open: false,
+            selected: 0,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        inventory: &mut Vec<String>,
+        equipment: &mut Equipment,
+        items: &HashMap<String, ItemConfig>,
+    ) {
+        if is_key_pressed(KeyCode::I) {
+            self.is_open = !self.is_open;
+            self.selected = 0;
+        }
+
+        if !self.is_open {
+            return;
+        }
+
+        // Rows are the equipment slots followed by the inventory contents
+        let row_count = EquipmentSlot::ALL.len() + inventory.len();
+        if is_key_pressed(KeyCode::Up) && self.selected > 0 {
+            self.selected -= 1;
+        }
+        if is_key_pressed(KeyCode::Down) && self.selected + 1 < row_count {
+            self.selected += 1;
+        }
+
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::E) {
+            if self.selected < EquipmentSlot::ALL.len() {
+                equipment.unequip_to_inventory(EquipmentSlot::ALL[self.selected], inventory);
+            } else {
+                equipment.equip_from_inventory(inventory, self.selected - EquipmentSlot::ALL.len(), items);
+            }
+
+            let row_count = EquipmentSlot::ALL.len() + inventory.len();
+            self.selected = self.selected.min(row_count - 1);
+        }
+    }
+
+    pub fn draw(
+        &self,
+        inventory: &[String],
+        equipment: &Equipment,
+        items: &HashMap<String, ItemConfig>,
+        stats: &PlayerStats,
+    ) {
+        if !self.is_open {
+            return;
+        }
+
+        let panel_width = 320.0;
+        let panel_height = 190.0 + inventory.len().max(1) as f32 * 22.0;
+        let panel_x = screen_width() / 2.0 - panel_width / 2.0;
+        let panel_y = screen_height() / 2.0 - panel_height / 2.0;
+
+        draw_rectangle(panel_x, panel_y, panel_width, panel_height, Color::new(0.0, 0.0, 0.0, 0.85));
+        draw_rectangle_lines(panel_x, panel_y, panel_width, panel_height, 2.0, WHITE);
+        draw_text("=== Equipment ===", panel_x + 10.0, panel_y + 25.0, 20.0, YELLOW);
+
+        let item_name = |id: &String| items.get(id).map(|item| item.name.clone()).unwrap_or_else(|| id.clone());
+
+        let mut y = panel_y + 50.0;
+        for (row, slot) in EquipmentSlot::ALL.iter().enumerate() {
+            let equipped = equipment.get(*slot).map(item_name).unwrap_or_else(|| "-".to_string());
+            let color = if row == self.selected { YELLOW } else { WHITE };
+            draw_text(&format!("{}: {}", slot.label(), equipped), panel_x + 20.0, y, 18.0, color);
+            y += 22.0;
+        }
+
+        draw_text("=== Inventory ===", panel_x + 10.0, y + 5.0, 20.0, YELLOW);
+        y += 30.0;
+        if inventory.is_empty() {
+            draw_text("(empty)", panel_x + 20.0, y, 18.0, GRAY);
+            y += 22.0;
+        }
+        for (i, item_id) in inventory.iter().enumerate() {
+            let row = EquipmentSlot::ALL.len() + i;
+            let color = if row == self.selected { YELLOW } else { WHITE };
+            draw_text(&item_name(item_id), panel_x + 20.0, y, 18.0, color);
+            y += 22.0;
+        }
+
+        draw_text(
+            &format!("ATK: {}  DEF: {}  Max HP: {}", stats.attack, stats.defense, stats.max_hp),
+            panel_x + 10.0,
+            y + 5.0,
+            18.0,
+            GREEN,
+        );
+        draw_text("UP/DOWN: Select, ENTER: Equip/Unequip, I: Close", panel_x + 10.0, y + 28.0, 14.0, GRAY);
+    }
+}
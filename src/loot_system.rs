@@ -0,0 +1,135 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::biome_system::ItemConfig;
+use crate::LootData;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LootDrop {
+    pub item: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+// Small xorshift generator so a given seed always yields the same drops
+pub struct LootRoller {
+    state: u64,
+}
+
+impl LootRoller {
+    pub fn new(seed: u64) -> Self {
+        LootRoller {
+            // xorshift gets stuck on a zero state
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Rolls item_chance first, then picks one entry from the weighted table
+    pub fn roll(&mut self, loot: &LootData) -> Option<String> {
+        let total_weight: u64 = loot.drops.iter().map(|drop| drop.weight as u64).sum();
+        if total_weight == 0 || self.next_f32() >= loot.item_chance {
+            return None;
+        }
+
+        let mut pick = self.next_u64() % total_weight;
+        for drop in &loot.drops {
+            if pick < drop.weight as u64 {
+                return Some(drop.item.clone());
+            }
+            pick -= drop.weight as u64;
+        }
+
+        None
+    }
+}
+
+// Reads `--seed <n>` from the command line
+pub fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|value| value.parse().ok())
+}
+
+pub fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(1)
+}
+
+// Drops entries that don't name an item in GameConfig.items
+pub fn validate_drops(monster_name: &str, loot: &mut LootData, items: &HashMap<String, ItemConfig>) {
+    loot.drops.retain(|drop| {
+        let known = items.contains_key(&drop.item);
+        if !known {
+            println!("Unknown loot item '{}' in drop table of {}", drop.item, monster_name);
+        }
+        known
+    });
+}
+
+pub struct ItemPickup {
+    pub item_id: String,
+    pub position: Vec2,
+    pub lifetime: f32,
+}
+
+impl ItemPickup {
+    pub fn new(item_id: String, position: Vec2) -> Self {
+        ItemPickup {
+            item_id,
+            position,
+            lifetime: 60.0,
+        }
+    }
+}
+
+fn pickup_color(item: Option<&ItemConfig>) -> Color {
+    match item.map(|item| item.item_type.as_str()) {
+        Some("consumable") => PINK,
+        Some("weapon") => LIGHTGRAY,
+        Some("armor") => BROWN,
+        Some("accessory") => GOLD,
+        _ => WHITE,
+    }
+}
+
+pub fn draw_pickups(pickups: &[ItemPickup], items: &HashMap<String, ItemConfig>, camera_offset: Vec2, time: f32) {
+    for pickup in pickups {
+        let item = items.get(&pickup.item_id);
+        let bob = (time * 3.0 + pickup.position.x).sin() * 3.0;
+        let screen_pos = pickup.position + camera_offset + vec2(0.0, bob);
+
+        // Blink during the last few seconds before despawning
+        if pickup.lifetime < 5.0 && (pickup.lifetime * 8.0) as i32 % 2 == 0 {
+            continue;
+        }
+
+        draw_poly(screen_pos.x, screen_pos.y, 4, 8.0, 45.0, pickup_color(item));
+        draw_poly_lines(screen_pos.x, screen_pos.y, 4, 8.0, 45.0, 2.0, BLACK);
+
+        if let Some(item) = item {
+            let text_width = measure_text(&item.name, None, 14, 1.0).width;
+            draw_text(&item.name, screen_pos.x - text_width / 2.0, screen_pos.y - 14.0, 14.0, WHITE);
+        }
+    }
+}
@@ -1,12 +1,16 @@
+mod color_system;
 mod sprite_system;
 mod biome_system;
-mod chunk_system;
+mod equipment_system;
 
 use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::collections::HashMap;
-use sprite_system::{SpriteRenderer, load_sprites};
+use color_system::parse_color;
+use sprite_system::{Animator, Facing, Heading, SpriteRenderer, load_sprites};
+use biome_system::load_game_config;
+use equipment_system::{Equipment, InventoryPanel, calculate_stats, contact_damage, draw_equipment_overlay};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Monster {
@@ -18,6 +22,18 @@ struct Monster {
     color: String,
     behavior: Vec<String>,
     loot: LootData,
+    // Sprite drawn instead of the one picked by name
+    #[serde(default)]
+    sprite: Option<String>,
+    // Sprite palette colors swapped for this monster
+    #[serde(default)]
+    palette: HashMap<String, String>,
+}
+
+impl Monster {
+    fn sprite_name(&self) -> &str {
+        self.sprite.as_deref().unwrap_or_else(|| monster_sprite_name(&self.name))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -38,18 +54,22 @@ struct ActiveMonster {
     current_hp: i32,
     is_dead: bool,
     respawn_timer: f32,
+    animator: Animator,
+    heading: Heading,
 }
 
 struct Player {
     position: Vec2,
     hp: i32,
-    max_hp: i32,
-    attack: i32,
+    level: i32,
     radius: f32,
     attack_cooldown: f32,
+    hurt_cooldown: f32,
     is_attacking: bool,
-    facing_left: bool,
-    animation_timer: f32,
+    heading: Heading,
+    animator: Animator,
+    inventory: Vec<String>,
+    equipment: Equipment,
 }
 
 struct DamageText {
@@ -66,6 +86,65 @@ struct MapData {
     tile_types: HashMap<String, TileType>,
     layout: Vec<String>,
     monster_spawns: Option<Vec<MonsterSpawn>>,
+    // The layout already looked up; built by load_map
+    #[serde(skip)]
+    grid: TileGrid,
+}
+
+// Marks a layout character with no tile type
+const NO_TILE: u16 = u16::MAX;
+
+// The layout as one index per tile, row by row, into `tiles`, and one bit
+// per tile saying whether it can be walked on
+#[derive(Debug, Clone, Default)]
+struct TileGrid {
+    width: usize,
+    height: usize,
+    tiles: Vec<TileType>,
+    ids: Vec<u16>,
+    walkable: Vec<u64>,
+}
+
+impl TileGrid {
+    fn compile(map: &MapData) -> TileGrid {
+        let mut keys: Vec<&String> = map.tile_types.keys().collect();
+        keys.sort();
+        let index: HashMap<char, u16> = keys.iter().enumerate()
+            .filter_map(|(i, key)| Some((key.chars().next()?, i as u16)))
+            .collect();
+        
+        let width = map.map_info.width;
+        let height = map.layout.len();
+        let mut grid = TileGrid {
+            width,
+            height,
+            tiles: keys.iter().map(|key| map.tile_types[*key].clone()).collect(),
+            ids: vec![NO_TILE; width * height],
+            walkable: vec![0; (width * height).div_ceil(64)],
+        };
+        for (y, row) in map.layout.iter().enumerate() {
+            for (x, tile_char) in row.chars().enumerate().take(width) {
+                let i = y * width + x;
+                grid.ids[i] = index.get(&tile_char).copied().unwrap_or(NO_TILE);
+                if grid.tile_at(x, y).is_some_and(|tile| tile.walkable) {
+                    grid.walkable[i / 64] |= 1 << (i % 64);
+                }
+            }
+        }
+        grid
+    }
+    
+    fn tile_at(&self, x: usize, y: usize) -> Option<&TileType> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles.get(self.ids[y * self.width + x] as usize)
+    }
+    
+    fn is_walkable(&self, x: usize, y: usize) -> bool {
+        let i = y * self.width + x;
+        x < self.width && y < self.height && self.walkable[i / 64] & (1 << (i % 64)) != 0
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -101,34 +180,10 @@ struct Camera {
     position: Vec2,
 }
 
-fn string_to_color(color: &str) -> Color {
-    match color {
-        "green" => GREEN,
-        "darkgreen" => Color::new(0.0, 0.5, 0.0, 1.0),
-        "red" => RED,
-        "blue" => BLUE,
-        "purple" => PURPLE,
-        "yellow" => YELLOW,
-        "gray" => GRAY,
-        "lightgray" => LIGHTGRAY,
-        "darkgray" => DARKGRAY,
-        "brown" => Color::new(0.5, 0.25, 0.0, 1.0),
-        "pink" => PINK,
-        _ => WHITE,
-    }
-}
-
 fn check_collision(pos1: Vec2, radius1: f32, pos2: Vec2, radius2: f32) -> bool {
     (pos1 - pos2).length() < radius1 + radius2
 }
 
-fn tile_to_world(tile_x: usize, tile_y: usize, tile_size: f32) -> Vec2 {
-    vec2(
-        tile_x as f32 * tile_size + tile_size / 2.0,
-        tile_y as f32 * tile_size + tile_size / 2.0
-    )
-}
-
 fn world_to_tile(world_pos: Vec2, tile_size: f32) -> (usize, usize) {
     (
         (world_pos.x / tile_size) as usize,
@@ -136,37 +191,59 @@ fn world_to_tile(world_pos: Vec2, tile_size: f32) -> (usize, usize) {
     )
 }
 
+fn monster_sprite_name(monster_name: &str) -> &'static str {
+    match monster_name {
+        "Forest Goblin" => "forest_goblin",
+        "Wild Boar" => "wild_boar",
+        "Wolf" => "wolf",
+        "Slime" => "slime",
+        "Tree Ent" => "tree_sprite",
+        _ => "forest_goblin", // Default
+    }
+}
+
 fn is_position_walkable(pos: Vec2, map_data: &MapData) -> bool {
-    let (tile_x, tile_y) = world_to_tile(pos, map_data.map_info.tile_size);
-    
-    if tile_y >= map_data.layout.len() || tile_x >= map_data.map_info.width {
+    if pos.x < 0.0 || pos.y < 0.0 {
         return false;
     }
-    
-    if let Some(row) = map_data.layout.get(tile_y) {
-        if let Some(tile_char) = row.chars().nth(tile_x) {
-            let tile_key = tile_char.to_string();
-            if let Some(tile_type) = map_data.tile_types.get(&tile_key) {
-                return tile_type.walkable;
-            }
-        }
-    }
-    
-    false
+    let (tile_x, tile_y) = world_to_tile(pos, map_data.map_info.tile_size);
+    map_data.grid.is_walkable(tile_x, tile_y)
 }
 
 #[macroquad::main("RPG with Map System")]
 async fn main() {
-    let monster_templates = load_monsters();
+    let game_config = match load_game_config("data/game_config.json") {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Failed to load game config: {}", e);
+            return;
+        }
+    };
+    
+    let mut monster_templates = load_monsters();
     println!("Loaded monsters: {} types", monster_templates.len());
     
-    let map_data = load_map("data/maps/forest_map.yaml");
+    let map_data = load_map("data/maps/forest_map.yaml").unwrap_or_else(|e| {
+        println!("{}", e);
+        create_default_map()
+    });
     println!("Loaded map: {}", map_data.map_info.name);
     
     // Load sprites
     let sprite_renderer = match load_sprites("data/sprites/character_sprites.json") {
-        Ok(sprite_data) => {
+        Ok(mut sprite_data) => {
             println!("Sprites loaded successfully!");
+            // Monsters with their own colors get a recolored copy of their sprite
+            for monster in &mut monster_templates {
+                if monster.palette.is_empty() {
+                    continue;
+                }
+                let base = monster.sprite_name().to_string();
+                let recolor = format!("{}@{}", base, monster.name);
+                if sprite_data.add_recolor(&recolor, &base, &monster.palette) {
+                    monster.sprite = Some(recolor);
+                }
+            }
             SpriteRenderer::new(sprite_data)
         },
         Err(e) => {
@@ -181,6 +258,7 @@ async fn main() {
                 },
                 color_palette: HashMap::new(),
                 sprites: HashMap::new(),
+                sheets: HashMap::new(),
             })
         }
     };
@@ -193,16 +271,20 @@ async fn main() {
     
     let mut player = Player {
         position: spawn_pos,
-        hp: 100,
-        max_hp: 100,
-        attack: 15,
+        hp: game_config.player_config.starting_hp,
+        level: 1,
         radius: 10.0,
         attack_cooldown: 0.0,
+        hurt_cooldown: 0.0,
         is_attacking: false,
-        facing_left: false,
-        animation_timer: 0.0,
+        heading: Heading::default(),
+        animator: Animator::new(),
+        inventory: game_config.player_config.starting_inventory.clone(),
+        equipment: Equipment::default(),
     };
     
+    let mut inventory_panel = InventoryPanel::new();
+    
     // Create active monsters from spawn points
     let mut active_monsters: Vec<ActiveMonster> = Vec::new();
     if let Some(spawns) = &map_data.monster_spawns {
@@ -219,6 +301,8 @@ async fn main() {
                     current_hp: template.hp,
                     is_dead: false,
                     respawn_timer: 0.0,
+                    animator: Animator::new(),
+                    heading: Heading::default(),
                 });
             }
         }
@@ -240,23 +324,22 @@ async fn main() {
         // Player movement input
         let mut move_dir = vec2(0.0, 0.0);
         if !game_over {
+            inventory_panel.update(&mut player.inventory, &mut player.equipment, &game_config.items);
+            
             // Player movement with collision detection and wall sliding
             if is_key_down(KeyCode::W) { move_dir.y -= 1.0; }
             if is_key_down(KeyCode::S) { move_dir.y += 1.0; }
             if is_key_down(KeyCode::A) { move_dir.x -= 1.0; }
             if is_key_down(KeyCode::D) { move_dir.x += 1.0; }
             
+            player.animator.set_state(if move_dir.length() > 0.0 { "walk" } else { "idle" });
+            
+            // Opposite keys cancel out and keep the old facing
+            if let Some(facing) = Facing::from_vector(move_dir) {
+                player.heading.turn(facing);
+            }
+            
             if move_dir.length() > 0.0 {
-                // Update facing direction
-                if move_dir.x < 0.0 {
-                    player.facing_left = true;
-                } else if move_dir.x > 0.0 {
-                    player.facing_left = false;
-                }
-                
-                // Update animation timer
-                player.animation_timer += delta * 5.0; // Animation speed
-                
                 let normalized_move = move_dir.normalize();
                 let move_speed = 200.0 * delta;
                 let desired_pos = player.position + normalized_move * move_speed;
@@ -360,6 +443,7 @@ async fn main() {
             if is_key_pressed(KeyCode::Space) && player.attack_cooldown <= 0.0 {
                 player.is_attacking = true;
                 player.attack_cooldown = 0.5;
+                player.animator.play_once("attack");
             }
             
             // Update attack cooldown
@@ -367,9 +451,17 @@ async fn main() {
                 player.attack_cooldown -= delta;
             }
             
+            if player.hurt_cooldown > 0.0 {
+                player.hurt_cooldown -= delta;
+            }
+            
+            let stats = calculate_stats(&game_config.player_config, player.level, &player.equipment, &game_config.items);
+            
             // Monster AI and combat
             for monster in &mut active_monsters {
                 if !monster.is_dead {
+                    let start_position = monster.position;
+                    
                     // Simple AI: move towards player if aggressive
                     if monster.data.behavior.contains(&"aggressive".to_string()) {
                         let dir_to_player = player.position - monster.position;
@@ -411,27 +503,44 @@ async fn main() {
                             }
                         }
                     }
+                    monster.animator.set_state(if monster.position != start_position { "walk" } else { "idle" });
+                    if let Some(facing) = Facing::from_vector(monster.position - start_position) {
+                        monster.heading.turn(facing);
+                    }
                     
                     // Check collision with player
-                    let monster_radius = 15.0;
-                    if check_collision(player.position, player.radius, monster.position, monster_radius) {
+                    let (monster_center, monster_radius) = sprite_renderer.hitbox(monster.data.sprite_name(), monster.position, 2.0);
+                    if check_collision(player.position, player.radius, monster_center, monster_radius) {
+                        if let Some(facing) = Facing::from_vector(player.position - monster.position) {
+                            monster.heading.turn(facing);
+                        }
+                        
                         // Monster attacks player
-                        player.hp -= 1;
+                        if player.hurt_cooldown <= 0.0 {
+                            player.hp -= contact_damage(monster.data.attack, stats.defense);
+                            player.hurt_cooldown = 0.5;
+                            player.animator.play_once("hurt");
+                        }
                         
                         // Player attacks monster if attacking
                         if player.is_attacking {
-                            monster.current_hp -= player.attack;
+                            if let Some(facing) = Facing::from_vector(monster.position - player.position) {
+                                player.heading.turn(facing);
+                            }
+                            monster.current_hp -= stats.attack;
                             damage_texts.push(DamageText {
                                 position: monster.position + vec2(0.0, -30.0),
-                                text: format!("-{}", player.attack),
+                                text: format!("-{}", stats.attack),
                                 timer: 1.0,
                                 color: YELLOW,
                             });
                             player.is_attacking = false;
+                            monster.animator.play_once("hurt");
                             
                             if monster.current_hp <= 0 {
                                 monster.is_dead = true;
                                 monster.respawn_timer = 5.0;
+                                monster.animator.play_once("death");
                                 gold_collected += monster.data.loot.gold;
                                 
                                 damage_texts.push(DamageText {
@@ -456,9 +565,16 @@ async fn main() {
             // Check game over
             if player.hp <= 0 {
                 game_over = true;
+                player.animator.set_state("death");
             }
         }
         
+        // Advance sprite animations
+        player.animator.update(&sprite_renderer, "player", delta);
+        for monster in &mut active_monsters {
+            monster.animator.update(&sprite_renderer, monster.data.sprite_name(), delta);
+        }
+        
         // Update damage texts
         damage_texts.retain_mut(|dt| {
             dt.timer -= delta;
@@ -471,10 +587,9 @@ async fn main() {
         
         // Draw map
         let tile_size = map_data.map_info.tile_size;
-        for (y, row) in map_data.layout.iter().enumerate() {
-            for (x, tile_char) in row.chars().enumerate() {
-                let tile_key = tile_char.to_string();
-                if let Some(tile_type) = map_data.tile_types.get(&tile_key) {
+        for y in 0..map_data.grid.height {
+            for x in 0..map_data.grid.width {
+                if let Some(tile_type) = map_data.grid.tile_at(x, y) {
                     let world_pos = vec2(x as f32 * tile_size, y as f32 * tile_size);
                     let screen_pos = world_pos + camera_offset;
                     
@@ -486,7 +601,7 @@ async fn main() {
                             screen_pos.y,
                             tile_size,
                             tile_size,
-                            string_to_color(&tile_type.color)
+                            parse_color(&tile_type.color).unwrap_or(WHITE)
                         );
                         
                         // Draw tile borders for better visibility
@@ -506,12 +621,7 @@ async fn main() {
         // Draw player
         let player_screen_pos = player.position + camera_offset;
         
-        // Choose animation frame
-        let frame_name = if move_dir.length() > 0.0 {
-            if player.animation_timer.sin() > 0.0 { "walk1" } else { "idle" }
-        } else {
-            "idle"
-        };
+        let frame_name = player.animator.frame(&sprite_renderer, "player");
         
         // Draw player sprite
         sprite_renderer.draw_sprite_outlined(
@@ -519,10 +629,21 @@ async fn main() {
             frame_name,
             player_screen_pos,
             2.0, // Scale factor
-            player.facing_left,
+            player.heading,
             BLACK
         );
         
+        // Draw equipped gear on top of the player sprite
+        draw_equipment_overlay(
+            &sprite_renderer,
+            &player.equipment,
+            &game_config.items,
+            frame_name,
+            player_screen_pos,
+            2.0,
+            player.heading
+        );
+        
         // Draw attack indicator
         if player.attack_cooldown > 0.4 {
             draw_circle_lines(player_screen_pos.x, player_screen_pos.y, player.radius * 2.0, 3.0, YELLOW);
@@ -531,45 +652,50 @@ async fn main() {
         // Draw monsters
         for monster in &active_monsters {
             let monster_screen_pos = monster.position + camera_offset;
+            let sprite_name = monster.data.sprite_name();
+            
+            // Dead monsters stay on screen until their death animation ends
+            if monster.is_dead && monster.animator.is_playing("death") {
+                sprite_renderer.draw_sprite(
+                    sprite_name,
+                    monster.animator.frame(&sprite_renderer, sprite_name),
+                    monster_screen_pos,
+                    2.0,
+                    monster.heading
+                );
+            }
             
             if !monster.is_dead {
-                // Get sprite name based on monster name
-                let sprite_name = match monster.data.name.as_str() {
-                    "Forest Goblin" => "forest_goblin",
-                    "Wild Boar" => "wild_boar",
-                    "Wolf" => "wolf",
-                    "Slime" => "slime",
-                    "Tree Ent" => "tree_sprite",
-                    _ => "forest_goblin", // Default
-                };
-                
                 // Draw monster sprite
                 sprite_renderer.draw_sprite(
                     sprite_name,
-                    "idle",
+                    monster.animator.frame(&sprite_renderer, sprite_name),
                     monster_screen_pos,
                     2.0, // Scale factor
-                    false // Not flipped
+                    monster.heading
                 );
                 
+                // Labels and bars go around the sprite, whatever its size
+                let bounds = sprite_renderer.bounds(sprite_name, monster_screen_pos, 2.0)
+                    .unwrap_or(Rect::new(monster_screen_pos.x - 16.0, monster_screen_pos.y - 16.0, 32.0, 32.0));
+                
                 // Monster info
-                draw_text(&monster.data.name, monster_screen_pos.x - 50.0, monster_screen_pos.y - 20.0, 16.0, WHITE);
+                draw_text(&monster.data.name, bounds.center().x - 50.0, bounds.y - 4.0, 16.0, WHITE);
                 
                 // Health bar
-                let bar_width = 60.0;
+                let bar_width = bounds.w.max(60.0);
                 let bar_height = 6.0;
                 let hp_percent = monster.current_hp as f32 / monster.data.hp as f32;
-                let sprite_height = 16.0 * 2.0; // sprite height * scale
                 draw_rectangle(
-                    monster_screen_pos.x - bar_width/2.0, 
-                    monster_screen_pos.y + sprite_height/2.0 + 5.0, 
+                    bounds.center().x - bar_width/2.0, 
+                    bounds.bottom() + 5.0, 
                     bar_width, 
                     bar_height, 
                     DARKGRAY
                 );
                 draw_rectangle(
-                    monster_screen_pos.x - bar_width/2.0, 
-                    monster_screen_pos.y + sprite_height/2.0 + 5.0, 
+                    bounds.center().x - bar_width/2.0, 
+                    bounds.bottom() + 5.0, 
                     bar_width * hp_percent, 
                     bar_height, 
                     GREEN
@@ -592,15 +718,16 @@ async fn main() {
         }
         
         // UI (fixed position, not affected by camera)
-        draw_text(&format!("{}", map_data.map_info.name), 10.0, 30.0, 30.0, WHITE);
-        draw_text("WASD: Move, SPACE: Attack", 10.0, 60.0, 20.0, WHITE);
+        draw_text(&map_data.map_info.name, 10.0, 30.0, 30.0, WHITE);
+        draw_text("WASD: Move, SPACE: Attack, I: Equipment", 10.0, 60.0, 20.0, WHITE);
         draw_text(&format!("FPS: {}", get_fps()), 10.0, 90.0, 20.0, GREEN);
         
         // Player stats
+        let stats = calculate_stats(&game_config.player_config, player.level, &player.equipment, &game_config.items);
         draw_text("=== Player Stats ===", 10.0, 130.0, 20.0, YELLOW);
-        draw_text(&format!("HP: {}/{}", player.hp, player.max_hp), 10.0, 160.0, 18.0, 
+        draw_text(&format!("HP: {}/{}", player.hp, stats.max_hp), 10.0, 160.0, 18.0, 
                   if player.hp < 30 { RED } else { WHITE });
-        draw_text(&format!("ATK: {}", player.attack), 10.0, 185.0, 18.0, WHITE);
+        draw_text(&format!("LV: {}  ATK: {}  DEF: {}", player.level, stats.attack, stats.defense), 10.0, 185.0, 18.0, WHITE);
         draw_text(&format!("Gold: {}", gold_collected), 10.0, 210.0, 18.0, GOLD);
         
         // Attack cooldown indicator
@@ -621,10 +748,9 @@ async fn main() {
         draw_rectangle_lines(minimap_x, minimap_y, minimap_size, minimap_size, 2.0, WHITE);
         
         // Draw minimap tiles
-        for (y, row) in map_data.layout.iter().enumerate() {
-            for (x, tile_char) in row.chars().enumerate() {
-                let tile_key = tile_char.to_string();
-                if let Some(tile_type) = map_data.tile_types.get(&tile_key) {
+        for y in 0..map_data.grid.height {
+            for x in 0..map_data.grid.width {
+                if let Some(tile_type) = map_data.grid.tile_at(x, y) {
                     if !tile_type.walkable {
                         let mini_x = minimap_x + (x as f32 * tile_size * minimap_scale);
                         let mini_y = minimap_y + (y as f32 * tile_size * minimap_scale);
@@ -654,6 +780,8 @@ async fn main() {
             }
         }
         
+        inventory_panel.draw(&player.inventory, &player.equipment, &game_config.items, &stats);
+        
         // Game over screen
         if game_over {
             let text = "GAME OVER";
@@ -668,10 +796,10 @@ async fn main() {
             
             if is_key_pressed(KeyCode::R) {
                 // Reset game
-                player.hp = player.max_hp;
+                player.hp = stats.max_hp;
                 player.position = spawn_pos;
-                player.facing_left = false;
-                player.animation_timer = 0.0;
+                player.heading = Heading::default();
+                player.animator = Animator::new();
                 gold_collected = 0;
                 game_over = false;
                 
@@ -680,6 +808,7 @@ async fn main() {
                     monster.current_hp = monster.data.hp;
                     monster.is_dead = false;
                     monster.respawn_timer = 0.0;
+                    monster.animator = Animator::new();
                 }
             }
         }
@@ -692,8 +821,19 @@ fn load_monsters() -> Vec<Monster> {
     match fs::read_to_string("data/monsters/forest_monsters_en.yaml") {
         Ok(contents) => {
             match serde_yaml::from_str::<MonsterData>(&contents) {
-                Ok(data) => {
+                Ok(mut data) => {
                     println!("Monster data loaded successfully!");
+                    // A monster whose colors don't parse is left out
+                    data.monsters.retain(|monster| {
+                        let colors = std::iter::once(&monster.color).chain(monster.palette.values());
+                        match colors.map(|color| parse_color(color)).find_map(Result::err) {
+                            Some(e) => {
+                                println!("Monster {}: {}", monster.name, e);
+                                false
+                            },
+                            None => true,
+                        }
+                    });
                     data.monsters
                 },
                 Err(e) => {
@@ -713,29 +853,34 @@ fn load_monsters() -> Vec<Monster> {
                 color: "green".to_string(),
                 behavior: vec!["aggressive".to_string()],
                 loot: LootData { gold: 5, item_chance: 0.1 },
+                sprite: None,
+                palette: HashMap::new(),
             }]
         }
     }
 }
 
-fn load_map(path: &str) -> MapData {
+fn load_map(path: &str) -> Result<MapData, String> {
     match fs::read_to_string(path) {
         Ok(contents) => {
             match serde_yaml::from_str::<MapData>(&contents) {
-                Ok(data) => {
-                    println!("Map data loaded successfully!");
-                    data
+                Ok(mut data) => {
+                    for (key, tile_type) in &data.tile_types {
+                        if let Err(e) = parse_color(&tile_type.color) {
+                            return Err(format!("Map {} tile {}: {}", path, key, e));
+                        }
+                    }
+                    // A row wider than the map would lose its last tiles
+                    if let Some(y) = data.layout.iter().position(|row| row.chars().count() > data.map_info.width) {
+                        return Err(format!("Map {} row {} is wider than the map's {} tiles", path, y, data.map_info.width));
+                    }
+                    data.grid = TileGrid::compile(&data);
+                    Ok(data)
                 },
-                Err(e) => {
-                    println!("Map YAML parsing error: {}", e);
-                    create_default_map()
-                }
+                Err(e) => Err(format!("Map YAML parsing error: {}", e)),
             }
         },
-        Err(e) => {
-            println!("Map file read error: {}", e);
-            create_default_map()
-        }
+        Err(e) => Err(format!("Map file read error: {}", e)),
     }
 }
 
@@ -752,7 +897,7 @@ fn create_default_map() -> MapData {
         color: "gray".to_string(),
     });
     
-    MapData {
+    let mut map = MapData {
         map_info: MapInfo {
             name: "Default Map".to_string(),
             width: 20,
@@ -779,5 +924,8 @@ fn create_default_map() -> MapData {
             "####################".to_string(),
         ],
         monster_spawns: None,
-    }
+        grid: TileGrid::default(),
+    };
+    map.grid = TileGrid::compile(&map);
+    map
 }
\ No newline at end of file
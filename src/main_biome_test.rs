@@ -1,3 +1,4 @@
+mod color_system;
 mod sprite_system;
 mod biome_system;
 
@@ -5,6 +6,7 @@ use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::collections::HashMap;
+use color_system::parse_color;
 use sprite_system::{SpriteRenderer, load_sprites};
 use biome_system::{GameConfig, load_game_config};
 
@@ -74,32 +76,6 @@ struct BiomeData {
     sprite_renderer: SpriteRenderer,
 }
 
-fn parse_color(color_str: &str) -> Color {
-    if color_str.starts_with('#') {
-        let hex = &color_str[1..];
-        if hex.len() == 6 {
-            if let Ok(r) = u8::from_str_radix(&hex[0..2], 16) {
-                if let Ok(g) = u8::from_str_radix(&hex[2..4], 16) {
-                    if let Ok(b) = u8::from_str_radix(&hex[4..6], 16) {
-                        return Color::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0);
-                    }
-                }
-            }
-        }
-    }
-    
-    match color_str {
-        "red" => RED,
-        "green" => GREEN,
-        "darkgreen" => Color::new(0.0, 0.5, 0.0, 1.0),
-        "blue" => BLUE,
-        "yellow" => YELLOW,
-        "brown" => Color::new(0.5, 0.25, 0.0, 1.0),
-        "orange" => ORANGE,
-        _ => WHITE,
-    }
-}
-
 #[macroquad::main("Biome Test System")]
 async fn main() {
     // Load game configuration
@@ -151,7 +127,7 @@ async fn main() {
                             pos.y,
                             tile_size,
                             tile_size,
-                            parse_color(&tile_type.color)
+                            parse_color(&tile_type.color).unwrap_or(WHITE)
                         );
                     }
                 }
@@ -218,7 +194,14 @@ fn load_map(path: &str) -> Result<MapData, String> {
     match fs::read_to_string(path) {
         Ok(contents) => {
             match serde_yaml::from_str::<MapData>(&contents) {
-                Ok(data) => Ok(data),
+                Ok(data) => {
+                    for (key, tile_type) in &data.tile_types {
+                        if let Err(e) = parse_color(&tile_type.color) {
+                            return Err(format!("Map {} tile {}: {}", path, key, e));
+                        }
+                    }
+                    Ok(data)
+                },
                 Err(e) => Err(format!("Map YAML parsing error: {}", e)),
             }
         },
@@ -1,13 +1,48 @@
+mod color_system;
 mod sprite_system;
 mod biome_system;
 mod chunk_system;
+mod equipment_system;
+mod loot_system;
+mod shop_system;
+mod save_system;
+mod dialogue_system;
+mod quest_system;
+mod trigger_system;
+mod script_system;
+mod path_system;
+mod tiled_system;
+mod bench_system;
+mod sheet_system;
+mod editor_system;
 
 use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::collections::HashMap;
-use sprite_system::{SpriteRenderer, load_sprites};
-use chunk_system::{ChunkManager, load_world_config};
+use std::collections::{HashMap, HashSet};
+use color_system::parse_color;
+use sprite_system::{Animator, Facing, Heading, SpriteData, SpriteRenderer, load_sprites};
+use chunk_system::{ChunkManager, WorldConfig, load_world_config};
+use biome_system::load_game_config;
+use equipment_system::{Equipment, InventoryPanel, calculate_stats, contact_damage, draw_equipment_overlay};
+use loot_system::{ItemPickup, LootDrop, LootRoller, draw_pickups, random_seed, seed_from_args, validate_drops};
+use shop_system::{MerchantData, ShopPanel, INTERACT_RANGE, draw_merchant};
+use save_system::{
+    PlayerSave, SaveData, SaveMenu, SaveMenuAction, SaveMenuMode, AUTOSAVE_SLOT, SAVE_VERSION,
+    read_save, unix_time, write_save,
+};
+use dialogue_system::{DialogueBox, DialogueContext, NpcData, draw_npc};
+use quest_system::{
+    QuestEvent, QuestJournal, QuestLog, QuestUpdate, grant_xp, load_quests, validate_quests, xp_to_next_level,
+};
+use trigger_system::{FiredTrigger, TriggerAction, TriggerData, TriggerTracker, WarpTarget, WarpTransition};
+use script_system::{ScriptCommand, ScriptEngine, ScriptState, monster_map};
+use path_system::find_path;
+use editor_system::{EditorEvent, SpriteEditor};
+
+const SLIDE_GRIP: f32 = 10.0; // how fast velocity catches up with the keys, times friction
+const HAZARD_TICK: f32 = 0.5; // seconds between hits from harmful tiles
+const REPATH_INTERVAL: f32 = 0.5; // seconds between path searches of a chasing monster
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Monster {
@@ -19,12 +54,70 @@ pub struct Monster {
     color: String,
     behavior: Vec<String>,
     loot: LootData,
+    // Rhai behavior script with `fn update(monster, dt)`
+    #[serde(default)]
+    script: Option<String>,
+    // Sprite drawn instead of the one picked by name, so variants can share
+    // another monster's drawing
+    #[serde(default)]
+    sprite: Option<String>,
+    // Sprite palette colors swapped for this monster, keyed by palette character
+    #[serde(default)]
+    palette: HashMap<String, String>,
+    // The tier (elite, champion) this copy was strengthened for
+    #[serde(skip)]
+    tier: Option<String>,
+}
+
+impl Monster {
+    fn sprite_name(&self) -> &str {
+        self.sprite.as_deref().unwrap_or_else(|| monster_sprite_name(&self.name))
+    }
+    
+    fn with_tier(&self, tier_name: &str, tier: &MonsterTier) -> Monster {
+        let mut monster = self.clone();
+        monster.hp = (self.hp as f32 * tier.hp).round() as i32;
+        monster.attack = (self.attack as f32 * tier.attack).round() as i32;
+        monster.speed = self.speed * tier.speed;
+        monster.loot.gold = (self.loot.gold as f32 * tier.gold).round() as i32;
+        monster.palette.extend(tier.palette.iter().map(|(key, color)| (key.clone(), color.clone())));
+        monster.tier = Some(tier_name.to_string());
+        monster
+    }
+}
+
+// A stronger, richer grade of every monster that spawn points can ask for.
+// Stats are multiplied; the palette recolors whatever the tier's monsters
+// are drawn with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MonsterTier {
+    #[serde(default = "default_tier_multiplier")]
+    hp: f32,
+    #[serde(default = "default_tier_multiplier")]
+    attack: f32,
+    #[serde(default = "default_tier_multiplier")]
+    speed: f32,
+    #[serde(default = "default_tier_multiplier")]
+    gold: f32,
+    #[serde(default)]
+    palette: HashMap<String, String>,
+}
+
+fn default_tier_multiplier() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MonsterTierData {
+    tiers: HashMap<String, MonsterTier>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LootData {
     gold: i32,
     item_chance: f32,
+    #[serde(default)]
+    drops: Vec<LootDrop>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,18 +132,32 @@ pub struct ActiveMonster {
     pub current_hp: i32,
     pub is_dead: bool,
     pub respawn_timer: f32,
+    // False for monsters spawned by map events
+    pub respawns: bool,
+    // Tile centers still to walk through while chasing, and when to look again
+    pub path: Vec<Vec2>,
+    pub repath_timer: f32,
+    pub animator: Animator,
+    pub heading: Heading,
 }
 
 struct Player {
     position: Vec2,
     hp: i32,
-    max_hp: i32,
-    attack: i32,
+    level: i32,
+    xp: i32,
+    gold: i32,
     radius: f32,
+    // Kept between frames so slippery tiles can carry the player along
+    velocity: Vec2,
     attack_cooldown: f32,
+    hurt_cooldown: f32,
+    hazard_timer: f32,
     is_attacking: bool,
-    facing_left: bool,
-    animation_timer: f32,
+    heading: Heading,
+    animator: Animator,
+    inventory: Vec<String>,
+    equipment: Equipment,
 }
 
 struct DamageText {
@@ -64,8 +171,212 @@ struct DamageText {
 pub struct MapData {
     pub map_info: MapInfo,
     pub tile_types: HashMap<String, TileType>,
+    // Images that tile types can take their pictures from, by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tilesets: HashMap<String, TilesetInfo>,
+    // Single-layer maps only have a layout; load_map moves it into layers.ground
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub layout: Vec<String>,
+    #[serde(default)]
+    pub layers: MapLayers,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub monster_spawns: Option<Vec<MonsterSpawn>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merchants: Option<Vec<MerchantData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub npcs: Option<Vec<NpcData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub triggers: Option<Vec<TriggerData>>,
+    // The layers with every character already looked up; built by load_map
+    #[serde(skip)]
+    pub grid: TileGrid,
+}
+
+// Rows of tile characters, all keyed into the same tile_types. A space leaves
+// that cell of the layer empty.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MapLayers {
+    // Drawn first; decides walkability
+    #[serde(default)]
+    pub ground: Vec<String>,
+    // Drawn on top of the ground, below entities; unwalkable tiles block
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decoration: Vec<String>,
+    // Never drawn; a tile here overrides the walkability of the ones below
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collision: Vec<String>,
+    // Drawn above entities, so the player can walk behind roofs and treetops
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overhead: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapLayer {
+    Ground,
+    Decoration,
+    Collision,
+    Overhead,
+}
+
+// Marks an empty cell in a TileGrid layer
+pub const NO_TILE: u16 = u16::MAX;
+
+// Every layer as one index per cell, row by row, into `tiles`, plus one bit
+// per cell for what the layers add up to. Built once when the map loads so
+// drawing and collision never touch the strings.
+#[derive(Debug, Clone, Default)]
+pub struct TileGrid {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<TileType>,
+    layers: [Vec<u16>; 4],
+    walkable: Bitmap,
+    blocks_projectiles: Bitmap,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Bitmap {
+    words: Vec<u64>,
+}
+
+impl Bitmap {
+    fn new(len: usize) -> Self {
+        Bitmap { words: vec![0; len.div_ceil(64)] }
+    }
+    
+    fn get(&self, i: usize) -> bool {
+        self.words.get(i / 64).is_some_and(|word| word & (1 << (i % 64)) != 0)
+    }
+    
+    fn set(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+}
+
+impl TileGrid {
+    pub fn compile(map: &MapData) -> Result<TileGrid, String> {
+        // Sorted so a map always gets the same indices
+        let mut keys: Vec<&String> = map.tile_types.keys().collect();
+        keys.sort();
+        if keys.len() >= NO_TILE as usize {
+            return Err(format!("Map {} has too many tile types", map.map_info.name));
+        }
+        let index: HashMap<char, u16> = keys.iter().enumerate()
+            .filter_map(|(i, key)| Some((key.chars().next()?, i as u16)))
+            .collect();
+        
+        let width = map.map_info.width;
+        let height = map.layers.ground.len();
+        // A row wider than the map would lose its last tiles, often the wall
+        let compile_layer = |layer: &str, rows: &[String]| -> Result<Vec<u16>, String> {
+            let mut cells = vec![NO_TILE; width * height];
+            for (y, row) in rows.iter().enumerate().take(height) {
+                let row_width = row.chars().count();
+                if row_width > width {
+                    return Err(format!("Map {} {} row {} is {} tiles wide, but the map is {}", map.map_info.name, layer, y, row_width, width));
+                }
+                for (x, tile_char) in row.chars().enumerate() {
+                    cells[y * width + x] = index.get(&tile_char).copied().unwrap_or(NO_TILE);
+                }
+            }
+            Ok(cells)
+        };
+        
+        let mut grid = TileGrid {
+            width,
+            height,
+            tiles: keys.iter().map(|key| map.tile_types[*key].clone()).collect(),
+            layers: [
+                compile_layer("ground", &map.layers.ground)?,
+                compile_layer("decoration", &map.layers.decoration)?,
+                compile_layer("collision", &map.layers.collision)?,
+                compile_layer("overhead", &map.layers.overhead)?,
+            ],
+            walkable: Bitmap::new(width * height),
+            blocks_projectiles: Bitmap::new(width * height),
+        };
+        
+        for y in 0..height {
+            for x in 0..width {
+                // A collision tile overrides whatever is drawn there
+                let collision = grid.tile_at(MapLayer::Collision, x, y);
+                let surface = grid.tile_at(MapLayer::Decoration, x, y).or_else(|| grid.tile_at(MapLayer::Ground, x, y));
+                let walkable = match collision {
+                    Some(tile) => tile.walkable,
+                    None => {
+                        grid.tile_at(MapLayer::Ground, x, y).is_some_and(|tile| tile.walkable) &&
+                            grid.tile_at(MapLayer::Decoration, x, y).is_none_or(|tile| tile.walkable)
+                    },
+                };
+                let blocks_projectiles = collision.or(surface).is_none_or(|tile| tile.blocks_projectiles());
+                
+                if walkable {
+                    grid.walkable.set(y * width + x);
+                }
+                if blocks_projectiles {
+                    grid.blocks_projectiles.set(y * width + x);
+                }
+            }
+        }
+        
+        Ok(grid)
+    }
+    
+    pub fn index_at(&self, layer: MapLayer, x: usize, y: usize) -> u16 {
+        if x >= self.width || y >= self.height {
+            return NO_TILE;
+        }
+        self.layers[layer as usize][y * self.width + x]
+    }
+    
+    pub fn tile_at(&self, layer: MapLayer, x: usize, y: usize) -> Option<&TileType> {
+        self.tiles.get(self.index_at(layer, x, y) as usize)
+    }
+    
+    // Cells outside the map are never walkable and always block
+    pub fn is_walkable(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.walkable.get(y * self.width + x)
+    }
+    
+    pub fn blocks_projectiles(&self, x: usize, y: usize) -> bool {
+        x >= self.width || y >= self.height || self.blocks_projectiles.get(y * self.width + x)
+    }
+}
+
+impl MapData {
+    pub fn tile_at(&self, layer: MapLayer, x: usize, y: usize) -> Option<&TileType> {
+        self.grid.tile_at(layer, x, y)
+    }
+
+    // The tile whose movement properties apply: a decoration if there is one, else the ground
+    pub fn surface_at(&self, x: usize, y: usize) -> Option<&TileType> {
+        self.tile_at(MapLayer::Decoration, x, y).or_else(|| self.tile_at(MapLayer::Ground, x, y))
+    }
+
+    pub fn blocks_projectiles(&self, x: usize, y: usize) -> bool {
+        self.grid.blocks_projectiles(x, y)
+    }
+
+    pub fn is_walkable(&self, x: usize, y: usize) -> bool {
+        self.grid.is_walkable(x, y)
+    }
+
+    // The tile under a position measured in pixels from the map's top-left corner
+    pub fn tile_coords(&self, local_pos: Vec2) -> Option<(usize, usize)> {
+        if local_pos.x < 0.0 || local_pos.y < 0.0 {
+            return None;
+        }
+        let tile_size = self.map_info.tile_size;
+        let (x, y) = ((local_pos.x / tile_size) as usize, (local_pos.y / tile_size) as usize);
+        if x >= self.grid.width || y >= self.grid.height {
+            return None;
+        }
+        Some((x, y))
+    }
+
+    pub fn is_walkable_at(&self, local_pos: Vec2) -> bool {
+        self.tile_coords(local_pos).is_some_and(|(x, y)| self.is_walkable(x, y))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -88,6 +399,81 @@ pub struct TileType {
     pub name: String,
     pub walkable: bool,
     pub color: String,
+    // Scales movement speed while standing on the tile (sand, shallow water)
+    #[serde(default = "default_speed_multiplier")]
+    pub speed_multiplier: f32,
+    // Dealt to the player standing on or brushing against the tile (lava, cactus)
+    #[serde(default)]
+    pub damage_per_second: f32,
+    // 1.0 stops on the spot; lower values keep sliding, like ice
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+    // Defaults to blocking wherever the tile can't be walked on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks_projectiles: Option<bool>,
+    // Drawn instead of `color` when the tileset image loads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<TileTexture>,
+    // Edge overlay picked from which neighbours share the terrain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autotile: Option<Autotile>,
+}
+
+// 16 overlay tiles in a row, starting at `first`. The variant is first plus a
+// mask of the neighbours with the same terrain: north 1, east 2, south 4,
+// west 8. So `first + 15` is a tile surrounded by its own terrain.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Autotile {
+    pub terrain: String,
+    pub tileset: String,
+    #[serde(default)]
+    pub first: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TilesetInfo {
+    pub image: String,
+    // Width and height of one tile in the image; tiles are numbered row by row from 0
+    pub tile_size: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TileTexture {
+    pub tileset: String,
+    #[serde(default)]
+    pub tile: usize,
+    // Animated tiles cycle through these instead of showing `tile`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<usize>,
+    #[serde(default = "default_frame_time")]
+    pub frame_time: f32,
+}
+
+fn default_frame_time() -> f32 {
+    0.25
+}
+
+impl TileTexture {
+    pub fn tile_at(&self, time: f32) -> usize {
+        if self.frames.is_empty() || self.frame_time <= 0.0 {
+            return self.tile;
+        }
+        self.frames[(time / self.frame_time) as usize % self.frames.len()]
+    }
+}
+
+fn default_speed_multiplier() -> f32 {
+    1.0
+}
+
+fn default_friction() -> f32 {
+    1.0
+}
+
+impl TileType {
+    pub fn blocks_projectiles(&self) -> bool {
+        self.blocks_projectiles.unwrap_or(!self.walkable)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -95,39 +481,149 @@ pub struct MonsterSpawn {
     pub x: f32,
     pub y: f32,
     pub monster_type: String,
+    // Spawns a strengthened monster from data/monsters/tiers.yaml
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
 }
 
 struct Camera {
     position: Vec2,
 }
 
-pub fn string_to_color(color_str: &str) -> Color {
-    if color_str.starts_with('#') {
-        let hex = &color_str[1..];
-        if hex.len() == 6 {
-            if let Ok(r) = u8::from_str_radix(&hex[0..2], 16) {
-                if let Ok(g) = u8::from_str_radix(&hex[2..4], 16) {
-                    if let Ok(b) = u8::from_str_radix(&hex[4..6], 16) {
-                        return Color::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0);
+fn build_save(
+    player: &Player,
+    chunk_manager: &ChunkManager,
+    story_flags: &HashSet<String>,
+    quest_log: &QuestLog,
+    world_clock: f32,
+) -> SaveData {
+    let mut flags: Vec<String> = story_flags.iter().cloned().collect();
+    flags.sort();
+    
+    SaveData {
+        version: SAVE_VERSION,
+        saved_at: unix_time(),
+        world_clock,
+        story_flags: flags,
+        quests: quest_log.clone(),
+        player: PlayerSave {
+            position: [player.position.x, player.position.y],
+            chunk: chunk_manager.current_chunk.clone(),
+            outdoor_chunk: chunk_manager.outdoor_chunk().map(str::to_string),
+            hp: player.hp,
+            level: player.level,
+            xp: player.xp,
+            gold: player.gold,
+            inventory: player.inventory.clone(),
+            equipment: player.equipment.clone(),
+        },
+        chunks: chunk_manager.snapshot_chunk_states(),
+    }
+}
+
+fn apply_player_save(player: &mut Player, save: &PlayerSave) {
+    player.position = vec2(save.position[0], save.position[1]);
+    player.hp = save.hp;
+    player.level = save.level;
+    player.xp = save.xp;
+    player.gold = save.gold;
+    player.inventory = save.inventory.clone();
+    player.equipment = save.equipment.clone();
+    player.attack_cooldown = 0.0;
+    player.hurt_cooldown = 0.0;
+    player.hazard_timer = 0.0;
+    player.velocity = Vec2::ZERO;
+    player.is_attacking = false;
+    player.heading = Heading::default();
+    player.animator = Animator::new();
+}
+
+// Everything map events and scripts are allowed to change
+struct EventTargets<'a> {
+    player: &'a mut Player,
+    max_hp: i32,
+    chunk_manager: &'a mut ChunkManager,
+    monster_templates: &'a [Monster],
+    story_flags: &'a mut HashSet<String>,
+    trigger_tracker: &'a mut TriggerTracker,
+    warp_transition: &'a mut WarpTransition,
+    script_engine: &'a mut ScriptEngine,
+    world_clock: f32,
+}
+
+fn script_state(player: &Player, current_chunk: &str, story_flags: &HashSet<String>, world_clock: f32) -> ScriptState {
+    ScriptState {
+        player_pos: player.position,
+        hp: player.hp,
+        level: player.level,
+        gold: player.gold,
+        inventory: player.inventory.clone(),
+        flags: story_flags.clone(),
+        chunk: current_chunk.to_string(),
+        world_time: world_clock,
+        commands: Vec::new(),
+    }
+}
+
+fn run_trigger_actions(fired: Vec<FiredTrigger>, targets: &mut EventTargets) {
+    for trigger in fired {
+        for action in trigger.actions {
+            match action {
+                TriggerAction::ShowText(text) => targets.trigger_tracker.show_text(&text),
+                TriggerAction::Warp { chunk, x, y } => {
+                    // Land in the middle of the tile so the player doesn't overlap walls
+                    targets.warp_transition.start(WarpTarget {
+                        chunk: chunk.unwrap_or_else(|| trigger.chunk_id.clone()),
+                        x: x + 0.5,
+                        y: y + 0.5,
+                    });
+                },
+                TriggerAction::SpawnMonster { monster, x, y } => {
+                    let template = targets.monster_templates.iter().find(|m| m.name == monster && m.tier.is_none());
+                    let pos = targets.chunk_manager.chunk_id_tile_to_world(&trigger.chunk_id, x, y);
+                    match (template, pos) {
+                        (Some(template), Some(pos)) => {
+                            targets.chunk_manager.spawn_monster(&trigger.chunk_id, template, pos);
+                        },
+                        _ => println!("Cannot spawn {} in {}", monster, trigger.chunk_id),
                     }
-                }
+                },
+                TriggerAction::SetFlag(flag) => {
+                    targets.story_flags.insert(flag);
+                },
+                TriggerAction::ClearFlag(flag) => {
+                    targets.story_flags.remove(&flag);
+                },
+                TriggerAction::Script(path) => {
+                    let state = script_state(targets.player, &targets.chunk_manager.current_chunk, targets.story_flags, targets.world_clock);
+                    targets.script_engine.begin(state);
+                    targets.script_engine.run(&path);
+                    let commands = targets.script_engine.finish();
+                    run_script_commands(&trigger.chunk_id, commands, targets);
+                },
             }
         }
     }
+}
+
+fn run_script_commands(chunk_id: &str, commands: Vec<ScriptCommand>, targets: &mut EventTargets) {
+    let mut actions = Vec::new();
+    for command in commands {
+        match command {
+            ScriptCommand::Action(action) => actions.push(action),
+            ScriptCommand::GiveItem(item) => targets.player.inventory.push(item),
+            ScriptCommand::TakeItem(item) => {
+                if let Some(index) = targets.player.inventory.iter().position(|id| *id == item) {
+                    targets.player.inventory.remove(index);
+                }
+            },
+            ScriptCommand::GiveGold(gold) => targets.player.gold = (targets.player.gold + gold).max(0),
+            ScriptCommand::Heal(hp) => targets.player.hp = (targets.player.hp + hp).min(targets.max_hp),
+        }
+    }
     
-    match color_str {
-        "green" => GREEN,
-        "darkgreen" => Color::new(0.0, 0.5, 0.0, 1.0),
-        "red" => RED,
-        "blue" => BLUE,
-        "purple" => PURPLE,
-        "yellow" => YELLOW,
-        "gray" => GRAY,
-        "lightgray" => LIGHTGRAY,
-        "darkgray" => DARKGRAY,
-        "brown" => Color::new(0.5, 0.25, 0.0, 1.0),
-        "pink" => PINK,
-        _ => WHITE,
+    if !actions.is_empty() {
+        run_trigger_actions(vec![FiredTrigger { chunk_id: chunk_id.to_string(), actions }], targets);
     }
 }
 
@@ -135,8 +631,55 @@ fn check_collision(pos1: Vec2, radius1: f32, pos2: Vec2, radius2: f32) -> bool {
     (pos1 - pos2).length() < radius1 + radius2
 }
 
-#[macroquad::main("Seamless RPG World")]
-async fn main() {
+fn main() {
+    // Converting a Tiled map doesn't need a window
+    if let Some((input, output)) = tiled_system::import_args() {
+        match tiled_system::convert_file(&input, &output) {
+            Ok(()) => println!("Converted {} to {}", input, output),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+    
+    // So are converting sprites to PNG sheets and back
+    if let Some((input, output)) = sheet_system::export_args() {
+        match sheet_system::export_sprites(&input, &output) {
+            Ok(count) => println!("Exported {} sprites from {} to {}", count, input, output),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+    if let Some((input, output)) = sheet_system::import_args() {
+        match sheet_system::import_sheet(&input, &output) {
+            Ok(name) => println!("Imported {} into {} as {}", input, output, name),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+    
+    if let Some(path) = editor_system::editor_args() {
+        macroquad::Window::from_config(editor_system::window_conf(), editor_system::run_sprite_editor(path));
+        return;
+    }
+    
+    if bench_system::bench_requested() {
+        macroquad::Window::new("Sprite Benchmark", bench_system::run_sprite_benchmark());
+        return;
+    }
+    
+    macroquad::Window::new("Seamless RPG World", run());
+}
+
+async fn run() {
     // Load world configuration
     let world_config = match load_world_config("data/world_config.json") {
         Ok(config) => {
@@ -149,25 +692,77 @@ async fn main() {
         }
     };
     
+    let game_config = match load_game_config("data/game_config.json") {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Failed to load game config: {}", e);
+            return;
+        }
+    };
+    
     // Load all monster templates
     let mut all_monsters = Vec::new();
     all_monsters.extend(load_monsters("data/monsters/forest_monsters_en.yaml"));
     all_monsters.extend(load_monsters("data/monsters/desert_monsters.yaml"));
     println!("Loaded {} monster types", all_monsters.len());
     
-    // Load sprite data
-    let mut sprite_renderers = HashMap::new();
+    for monster in &mut all_monsters {
+        validate_drops(&monster.name, &mut monster.loot, &game_config.items);
+    }
+    
+    let quest_book = match load_quests("data/quests.yaml") {
+        Ok(book) => book,
+        Err(e) => {
+            println!("Failed to load quests: {}", e);
+            return;
+        }
+    };
+    let mut script_engine = ScriptEngine::new(&game_config.items);
+    for monster in &all_monsters {
+        if let Some(script) = &monster.script {
+            if let Err(e) = script_engine.load(script) {
+                println!("Script error: {}", e);
+            }
+        }
+    }
+    validate_quests(&quest_book, &all_monsters, &world_config, &game_config.items, &mut script_engine);
+    
+    // Tiered copies of every monster sit next to the plain ones, and spawns
+    // pick by name and tier
+    match load_monster_tiers("data/monsters/tiers.yaml") {
+        Ok(tiers) => {
+            let tiered: Vec<Monster> = tiers.iter()
+                .flat_map(|(tier_name, tier)| all_monsters.iter().map(|monster| monster.with_tier(tier_name, tier)))
+                .collect();
+            all_monsters.extend(tiered);
+        },
+        Err(e) => println!("Failed to load monster tiers: {}", e),
+    }
+    
+    let loot_seed = seed_from_args().unwrap_or_else(random_seed);
+    println!("Loot seed: {}", loot_seed);
+    let mut loot_roller = LootRoller::new(loot_seed);
+    
+    // Load sprite data by biome, kept as read so the sprite editor can
+    // save it back
+    let mut sprite_files: HashMap<String, (String, SpriteData)> = HashMap::new();
     
     // Load forest sprites
     if let Ok(sprite_data) = load_sprites("data/sprites/character_sprites.json") {
-        sprite_renderers.insert("forest".to_string(), SpriteRenderer::new(sprite_data));
+        sprite_files.insert("forest".to_string(), ("data/sprites/character_sprites.json".to_string(), sprite_data));
     }
     
     // Load desert sprites
     if let Ok(sprite_data) = load_sprites("data/sprites/desert_sprites.json") {
-        sprite_renderers.insert("desert".to_string(), SpriteRenderer::new(sprite_data));
+        sprite_files.insert("desert".to_string(), ("data/sprites/desert_sprites.json".to_string(), sprite_data));
     }
     
+    // Recolored monsters get their sprites baked in with the file's own
+    let recolors = monster_recolors(&mut all_monsters, &sprite_files);
+    let mut sprite_renderers: HashMap<String, SpriteRenderer> = sprite_files.iter()
+        .map(|(biome, (_, sprite_data))| (biome.clone(), bake_sprites(sprite_data, &recolors)))
+        .collect();
+    
     // Create chunk manager
     let mut chunk_manager = ChunkManager::new(world_config.clone());
     chunk_manager.initialize(&all_monsters);
@@ -181,18 +776,38 @@ async fn main() {
     
     let mut player = Player {
         position: spawn_pos,
-        hp: 100,
-        max_hp: 100,
-        attack: 15,
+        hp: game_config.player_config.starting_hp,
+        level: 1,
+        xp: 0,
+        gold: 0,
         radius: 10.0,
+        velocity: Vec2::ZERO,
         attack_cooldown: 0.0,
+        hurt_cooldown: 0.0,
+        hazard_timer: 0.0,
         is_attacking: false,
-        facing_left: false,
-        animation_timer: 0.0,
+        heading: Heading::default(),
+        animator: Animator::new(),
+        inventory: game_config.player_config.starting_inventory.clone(),
+        equipment: Equipment::default(),
     };
     
+    let mut inventory_panel = InventoryPanel::new();
+    let mut shop_panel = ShopPanel::new();
+    let mut save_menu = SaveMenu::new();
+    // The biome whose sprite file is open, and the editor
+    let mut sprite_editor: Option<(String, SpriteEditor)> = None;
+    let mut dialogue_box = DialogueBox::new();
+    let mut story_flags: HashSet<String> = HashSet::new();
+    let mut quest_log = QuestLog::default();
+    let mut quest_journal = QuestJournal::new();
+    let mut trigger_tracker = TriggerTracker::new();
+    let mut warp_transition = WarpTransition::new();
+    let mut save_notice: Option<(String, f32)> = None;
+    let mut world_clock = 0.0;
+    
     let mut damage_texts: Vec<DamageText> = Vec::new();
-    let mut gold_collected = 0;
+    let mut pickups: Vec<ItemPickup> = Vec::new();
     let mut game_over = false;
     let mut camera = Camera { position: player.position };
     
@@ -204,31 +819,157 @@ async fn main() {
         // Update camera to follow player
         camera.position = player.position;
         
-        // Update loaded chunks based on player position
-        chunk_manager.update_loaded_chunks(player.position, &all_monsters);
+        // Save / load menu actions
+        if let Some(action) = save_menu.update() {
+            match action {
+                SaveMenuAction::Save(slot) => {
+                    match write_save(&slot, &build_save(&player, &chunk_manager, &story_flags, &quest_log, world_clock)) {
+                        Ok(()) => save_notice = Some((format!("Saved to {}", slot), 2.0)),
+                        Err(e) => save_notice = Some((format!("Save failed: {}", e), 3.0)),
+                    }
+                    save_menu.close();
+                },
+                SaveMenuAction::Load(slot) => {
+                    match read_save(&slot) {
+                        Ok(save) => {
+                            apply_player_save(&mut player, &save.player);
+                            chunk_manager.restore(save.chunks, &save.player, &all_monsters);
+                            story_flags = save.story_flags.into_iter().collect();
+                            quest_log = save.quests;
+                            world_clock = save.world_clock;
+                            camera.position = player.position;
+                            pickups.clear();
+                            damage_texts.clear();
+                            game_over = false;
+                            save_notice = Some((format!("Loaded {}", slot), 2.0));
+                        },
+                        Err(e) => save_notice = Some((format!("Load failed: {}", e), 3.0)),
+                    }
+                    save_menu.close();
+                },
+            }
+        }
+        
+        // Warps happen at the darkest point of the fade
+        if let Some(target) = warp_transition.update(delta) {
+            match chunk_manager.warp(&target.chunk, target.x, target.y, &all_monsters) {
+                Some(pos) => {
+                    player.position = pos;
+                    player.velocity = Vec2::ZERO;
+                    camera.position = pos;
+                },
+                None => println!("Warp to unknown chunk {}", target.chunk),
+            }
+        }
+        
+        // Update loaded chunks based on player position, autosaving on every chunk crossing
+        if chunk_manager.update_loaded_chunks(player.position, &all_monsters) && !game_over {
+            match write_save(AUTOSAVE_SLOT, &build_save(&player, &chunk_manager, &story_flags, &quest_log, world_clock)) {
+                Ok(()) => save_notice = Some(("Autosaved".to_string(), 1.5)),
+                Err(e) => println!("Autosave failed: {}", e),
+            }
+        }
         
         // Player movement input
         let mut move_dir = vec2(0.0, 0.0);
-        if !game_over {
+        if let Some((biome, editor)) = &mut sprite_editor {
+            // Paused while editing sprites; saving shows the edits in the world
+            match editor.update() {
+                EditorEvent::Editing => {},
+                EditorEvent::Saved => {
+                    sprite_renderers.insert(biome.clone(), bake_sprites(editor.data(), &recolors));
+                    if let Some((_, sprite_data)) = sprite_files.get_mut(biome) {
+                        *sprite_data = editor.data().clone();
+                    }
+                },
+                EditorEvent::Closed => sprite_editor = None,
+            }
+        } else if save_menu.is_open() {
+            // Paused while the save menu is up
+        } else if shop_panel.is_open() {
+            // The world stays paused while trading
+            shop_panel.update(&mut player.gold, &mut player.inventory, &game_config.items);
+        } else if warp_transition.is_active() {
+            // Nothing moves during a warp fade
+        } else if dialogue_box.is_open() {
+            // ...and while talking
+            let mut ctx = DialogueContext {
+                level: player.level,
+                gold: &mut player.gold,
+                inventory: &mut player.inventory,
+                flags: &mut story_flags,
+            };
+            dialogue_box.update(delta, &mut ctx, &game_config.items);
+        } else if !game_over {
+            world_clock += delta;
+            
+            if is_key_pressed(KeyCode::F5) {
+                save_menu.open(SaveMenuMode::Save);
+            } else if is_key_pressed(KeyCode::F9) {
+                save_menu.open(SaveMenuMode::Load);
+            } else if is_key_pressed(KeyCode::F2) {
+                // The sprites of the biome the player is standing in
+                let biome = world_config.chunks.get(&chunk_manager.current_chunk)
+                    .map(|chunk_config| chunk_config.biome.as_str())
+                    .filter(|biome| sprite_files.contains_key(*biome))
+                    .unwrap_or("forest");
+                if let Some((path, sprite_data)) = sprite_files.get(biome) {
+                    sprite_editor = SpriteEditor::new(path.clone(), sprite_data.clone())
+                        .map(|editor| (biome.to_string(), editor));
+                }
+            }
+            
+            inventory_panel.update(&mut player.inventory, &mut player.equipment, &game_config.items);
+            quest_journal.update();
+            
+            // E talks to NPCs and merchants first; otherwise it goes to interact triggers
+            let mut interact_pressed = false;
+            if !inventory_panel.is_open && is_key_pressed(KeyCode::E) {
+                if let Some(npc) = chunk_manager.find_npc_near(player.position, INTERACT_RANGE) {
+                    let mut ctx = DialogueContext {
+                        level: player.level,
+                        gold: &mut player.gold,
+                        inventory: &mut player.inventory,
+                        flags: &mut story_flags,
+                    };
+                    dialogue_box.start(npc, &mut ctx, &game_config.items);
+                    quest_log.record(QuestEvent::TalkedTo(&npc.name), &quest_book);
+                } else if let Some(merchant) = chunk_manager.find_merchant_near(player.position, INTERACT_RANGE) {
+                    shop_panel.open(merchant.clone());
+                } else {
+                    interact_pressed = true;
+                }
+            }
+            
             if is_key_down(KeyCode::W) { move_dir.y -= 1.0; }
             if is_key_down(KeyCode::S) { move_dir.y += 1.0; }
             if is_key_down(KeyCode::A) { move_dir.x -= 1.0; }
             if is_key_down(KeyCode::D) { move_dir.x += 1.0; }
             
-            if move_dir.length() > 0.0 {
-                // Update facing direction
-                if move_dir.x < 0.0 {
-                    player.facing_left = true;
-                } else if move_dir.x > 0.0 {
-                    player.facing_left = false;
-                }
-                
-                // Update animation timer
-                player.animation_timer += delta * 5.0;
-                
-                let normalized_move = move_dir.normalize();
-                let move_speed = 200.0 * delta;
-                let desired_pos = player.position + normalized_move * move_speed;
+            player.animator.set_state(if move_dir.length() > 0.0 { "walk" } else { "idle" });
+            
+            // Opposite keys cancel out and keep the old facing
+            if let Some(facing) = Facing::from_vector(move_dir) {
+                player.heading.turn(facing);
+            }
+            
+            // The tile underneath sets how fast the player goes and how quickly
+            // the velocity follows the keys
+            let (speed_multiplier, friction) = match chunk_manager.surface_at(player.position) {
+                Some(tile) => (tile.speed_multiplier, tile.friction),
+                None => (1.0, 1.0),
+            };
+            let target_velocity = move_dir.normalize_or_zero() * 200.0 * speed_multiplier;
+            if friction >= 1.0 {
+                player.velocity = target_velocity;
+            } else {
+                let grip = (friction.max(0.0) * SLIDE_GRIP * delta).min(1.0);
+                player.velocity += (target_velocity - player.velocity) * grip;
+            }
+            
+            if player.velocity.length() > 1.0 {
+                let step = player.velocity * delta;
+                let desired_pos = player.position + step;
                 
                 // Check if we can move to the desired position
                 let check_position = |pos: Vec2| -> bool {
@@ -254,35 +995,77 @@ async fn main() {
                     let mut final_pos = player.position;
                     
                     // Try horizontal movement
-                    if move_dir.x != 0.0 {
-                        let horizontal_pos = vec2(
-                            player.position.x + normalized_move.x * move_speed,
-                            player.position.y
-                        );
-                        if check_position(horizontal_pos) {
-                            final_pos.x = horizontal_pos.x;
-                        }
+                    let horizontal_pos = vec2(player.position.x + step.x, player.position.y);
+                    if step.x != 0.0 && check_position(horizontal_pos) {
+                        final_pos.x = horizontal_pos.x;
+                    } else {
+                        player.velocity.x = 0.0;
                     }
                     
                     // Try vertical movement
-                    if move_dir.y != 0.0 {
-                        let vertical_pos = vec2(
-                            final_pos.x,
-                            player.position.y + normalized_move.y * move_speed
-                        );
-                        if check_position(vertical_pos) {
-                            final_pos.y = vertical_pos.y;
-                        }
+                    let vertical_pos = vec2(final_pos.x, player.position.y + step.y);
+                    if step.y != 0.0 && check_position(vertical_pos) {
+                        final_pos.y = vertical_pos.y;
+                    } else {
+                        player.velocity.y = 0.0;
                     }
                     
                     player.position = final_pos;
                 }
+            } else {
+                player.velocity = Vec2::ZERO;
+            }
+            
+            // Harmful tiles under or right next to the player hurt in ticks
+            let touching = [
+                vec2(0.0, 0.0),
+                vec2(-1.0, -1.0),
+                vec2(1.0, -1.0),
+                vec2(-1.0, 1.0),
+                vec2(1.0, 1.0),
+            ];
+            let hazard = touching.iter()
+                .filter_map(|offset| chunk_manager.surface_at(player.position + *offset * (player.radius + 2.0)))
+                .map(|tile| tile.damage_per_second)
+                .fold(0.0, f32::max);
+            if hazard > 0.0 {
+                player.hazard_timer += delta;
+                if player.hazard_timer >= HAZARD_TICK {
+                    player.hazard_timer -= HAZARD_TICK;
+                    let damage = ((hazard * HAZARD_TICK).round() as i32).max(1);
+                    player.hp -= damage;
+                    player.animator.play_once("hurt");
+                    damage_texts.push(DamageText {
+                        position: player.position + vec2(0.0, -30.0),
+                        text: format!("-{}", damage),
+                        timer: 0.8,
+                        color: ORANGE,
+                    });
+                }
+            } else {
+                player.hazard_timer = 0.0;
             }
             
+            // Map event triggers
+            let fired = trigger_tracker.update(&chunk_manager, player.position, interact_pressed, &story_flags);
+            let max_hp = calculate_stats(&game_config.player_config, player.level, &player.equipment, &game_config.items).max_hp;
+            run_trigger_actions(fired, &mut EventTargets {
+                player: &mut player,
+                max_hp,
+                chunk_manager: &mut chunk_manager,
+                monster_templates: &all_monsters,
+                story_flags: &mut story_flags,
+                trigger_tracker: &mut trigger_tracker,
+                warp_transition: &mut warp_transition,
+                script_engine: &mut script_engine,
+                world_clock,
+            });
+            
             // Attack input
             if is_key_pressed(KeyCode::Space) && player.attack_cooldown <= 0.0 {
                 player.is_attacking = true;
                 player.attack_cooldown = 0.5;
+                player.animator.play_once("attack");
             }
             
             // Update attack cooldown
@@ -290,57 +1073,139 @@ async fn main() {
                 player.attack_cooldown -= delta;
             }
             
+            if player.hurt_cooldown > 0.0 {
+                player.hurt_cooldown -= delta;
+            }
+            
+            let stats = calculate_stats(&game_config.player_config, player.level, &player.equipment, &game_config.items);
+            
             // Monster AI and combat - process all loaded chunks
             let chunk_ids = chunk_manager.get_loaded_chunk_ids();
             for chunk_id in chunk_ids {
-                if let Some(chunk) = chunk_manager.get_chunk_mut(&chunk_id) {
-                    // First, collect monster updates
-                    let mut monster_updates = Vec::new();
+                // First, collect monster updates
+                let mut monster_updates = Vec::new();
+                script_engine.begin(script_state(&player, &chunk_manager.current_chunk, &story_flags, world_clock));
+                if let Some(chunk) = chunk_manager.chunks.get(&chunk_id) {
                     for (i, monster) in chunk.active_monsters.iter().enumerate() {
-                        if !monster.is_dead && monster.data.behavior.contains(&"aggressive".to_string()) {
-                            let dir_to_player = player.position - monster.position;
-                            if dir_to_player.length() > 0.0 && dir_to_player.length() < 300.0 {
-                                let dir = dir_to_player.normalize();
-                                let move_speed = monster.data.speed * 30.0 * delta;
-                                let desired_pos = monster.position + dir * move_speed;
-                                monster_updates.push((i, desired_pos));
-                            }
+                        if monster.is_dead {
+                            continue;
                         }
+                        
+                        // Scripted behavior first; no answer falls back to the built-in AI
+                        let scripted_dir = monster.data.script.as_ref().and_then(|script| {
+                            let map = monster_map(&monster.data.name, monster.position, monster.current_hp, monster.data.hp, monster.data.speed);
+                            script_engine.monster_update(script, map, delta)
+                        });
+                        
+                        let mut path = monster.path.clone();
+                        let mut repath_timer = monster.repath_timer - delta;
+                        let dir = match scripted_dir {
+                            Some(dir) => dir.clamp_length_max(1.0),
+                            None if monster.data.behavior.contains(&"aggressive".to_string()) => {
+                                // Chase nearby players, walking around walls and bad ground
+                                let distance = (player.position - monster.position).length();
+                                if distance == 0.0 || distance >= 300.0 {
+                                    continue;
+                                }
+                                if repath_timer <= 0.0 {
+                                    path = find_path(&chunk_manager, monster.position, player.position).unwrap_or_default();
+                                    repath_timer = REPATH_INTERVAL;
+                                }
+                                while path.first().is_some_and(|waypoint| (*waypoint - monster.position).length() < 4.0) {
+                                    path.remove(0);
+                                }
+                                let target = path.first().copied().unwrap_or(player.position);
+                                (target - monster.position).normalize_or_zero()
+                            },
+                            None => continue,
+                        };
+                        
+                        let speed_multiplier = chunk_manager.surface_at(monster.position).map_or(1.0, |tile| tile.speed_multiplier);
+                        let move_speed = monster.data.speed * 30.0 * speed_multiplier * delta;
+                        let desired_pos = monster.position + dir * move_speed;
+                        let position = if chunk_manager.is_position_walkable(desired_pos) { desired_pos } else { monster.position };
+                        monster_updates.push((i, position, path, repath_timer));
                     }
-                    
-                    // Apply movement updates
-                    for (i, desired_pos) in monster_updates {
-                        if chunk_manager.is_position_walkable(desired_pos) {
-                            chunk.active_monsters[i].position = desired_pos;
+                }
+                
+                // Apply movement updates
+                if let Some(chunk) = chunk_manager.get_chunk_mut(&chunk_id) {
+                    for (i, monster) in chunk.active_monsters.iter_mut().enumerate() {
+                        let moving = monster_updates.iter().any(|(j, position, ..)| *j == i && *position != monster.position);
+                        monster.animator.set_state(if moving { "walk" } else { "idle" });
+                    }
+                    for (i, position, path, repath_timer) in monster_updates {
+                        let monster = &mut chunk.active_monsters[i];
+                        if let Some(facing) = Facing::from_vector(position - monster.position) {
+                            monster.heading.turn(facing);
                         }
+                        monster.position = position;
+                        monster.path = path;
+                        monster.repath_timer = repath_timer;
                     }
                 }
+                
+                let commands = script_engine.finish();
+                if !commands.is_empty() {
+                    let max_hp = calculate_stats(&game_config.player_config, player.level, &player.equipment, &game_config.items).max_hp;
+                    run_script_commands(&chunk_id, commands, &mut EventTargets {
+                        player: &mut player,
+                        max_hp,
+                        chunk_manager: &mut chunk_manager,
+                        monster_templates: &all_monsters,
+                        story_flags: &mut story_flags,
+                        trigger_tracker: &mut trigger_tracker,
+                        warp_transition: &mut warp_transition,
+                        script_engine: &mut script_engine,
+                        world_clock,
+                    });
+                }
             }
             
             // Handle combat separately
             for chunk_id in chunk_manager.get_loaded_chunk_ids() {
+                let renderer = biome_sprites(&sprite_renderers, &world_config, &chunk_id);
+                // Monsters touching the player, unless something that blocks
+                // projectiles is between them: hits don't go through walls
+                let touching: Vec<bool> = chunk_manager.chunks.get(&chunk_id)
+                    .map(|chunk| chunk.active_monsters.iter().map(|monster| {
+                        let (monster_center, monster_radius) = renderer.hitbox(monster.data.sprite_name(), monster.position, 2.0);
+                        check_collision(player.position, player.radius, monster_center, monster_radius) &&
+                            chunk_manager.has_clear_line(player.position, monster.position)
+                    }).collect())
+                    .unwrap_or_default();
                 if let Some(chunk) = chunk_manager.get_chunk_mut(&chunk_id) {
-                    for monster in &mut chunk.active_monsters {
+                    for (i, monster) in chunk.active_monsters.iter_mut().enumerate() {
                         if !monster.is_dead {
-                            // Check collision with player
-                            let monster_radius = 15.0;
-                            if check_collision(player.position, player.radius, monster.position, monster_radius) {
-                                player.hp -= 1;
+                            if touching[i] {
+                                if let Some(facing) = Facing::from_vector(player.position - monster.position) {
+                                    monster.heading.turn(facing);
+                                }
+                                if player.hurt_cooldown <= 0.0 {
+                                    player.hp -= contact_damage(monster.data.attack, stats.defense);
+                                    player.hurt_cooldown = 0.5;
+                                    player.animator.play_once("hurt");
+                                }
                                 
                                 if player.is_attacking {
-                                    monster.current_hp -= player.attack;
+                                    if let Some(facing) = Facing::from_vector(monster.position - player.position) {
+                                        player.heading.turn(facing);
+                                    }
+                                    monster.current_hp -= stats.attack;
                                     damage_texts.push(DamageText {
                                         position: monster.position + vec2(0.0, -30.0),
-                                        text: format!("-{}", player.attack),
+                                        text: format!("-{}", stats.attack),
                                         timer: 1.0,
                                         color: YELLOW,
                                     });
                                     player.is_attacking = false;
+                                    monster.animator.play_once("hurt");
                                     
                                     if monster.current_hp <= 0 {
                                         monster.is_dead = true;
                                         monster.respawn_timer = 5.0;
-                                        gold_collected += monster.data.loot.gold;
+                                        monster.animator.play_once("death");
+                                        player.gold += monster.data.loot.gold;
                                         
                                         damage_texts.push(DamageText {
                                             position: monster.position,
@@ -348,10 +1213,16 @@ async fn main() {
                                             timer: 2.0,
                                             color: GOLD,
                                         });
+                                        
+                                        if let Some(item_id) = loot_roller.roll(&monster.data.loot) {
+                                            pickups.push(ItemPickup::new(item_id, monster.position));
+                                        }
+                                        
+                                        quest_log.record(QuestEvent::Killed(&monster.data.name), &quest_book);
                                     }
                                 }
                             }
-                        } else {
+                        } else if monster.respawns {
                             // Handle respawn
                             monster.respawn_timer -= delta;
                             if monster.respawn_timer <= 0.0 {
@@ -360,12 +1231,97 @@ async fn main() {
                             }
                         }
                     }
+                    
+                    // Event-spawned monsters don't come back
+                    chunk.active_monsters.retain(|monster| {
+                        monster.respawns || !monster.is_dead || monster.animator.is_playing("death")
+                    });
                 }
             }
             
+            // Collect item pickups the player walks over
+            pickups.retain_mut(|pickup| {
+                pickup.lifetime -= delta;
+                if check_collision(player.position, player.radius, pickup.position, 8.0) {
+                    if let Some(item) = game_config.items.get(&pickup.item_id) {
+                        damage_texts.push(DamageText {
+                            position: pickup.position,
+                            text: format!("+{}", item.name),
+                            timer: 2.0,
+                            color: SKYBLUE,
+                        });
+                    }
+                    player.inventory.push(pickup.item_id.clone());
+                    return false;
+                }
+                pickup.lifetime > 0.0
+            });
+            
             // Check game over
             if player.hp <= 0 {
                 game_over = true;
+                player.animator.set_state("death");
+            }
+        }
+        
+        // Advance sprite animations, each with the sprites of its chunk's biome
+        let player_chunk = chunk_manager.get_chunk_at_position(player.position).unwrap_or_default();
+        player.animator.update(biome_sprites(&sprite_renderers, &world_config, &player_chunk), "player", delta);
+        for chunk_id in chunk_manager.get_loaded_chunk_ids() {
+            let renderer = biome_sprites(&sprite_renderers, &world_config, &chunk_id);
+            if let Some(chunk) = chunk_manager.get_chunk_mut(&chunk_id) {
+                for monster in &mut chunk.active_monsters {
+                    monster.animator.update(renderer, monster.data.sprite_name(), delta);
+                }
+            }
+        }
+        
+        // Start and advance quests, handing out rewards for finished ones
+        if !game_over {
+            script_engine.begin(script_state(&player, &chunk_manager.current_chunk, &story_flags, world_clock));
+            let updates = quest_log.refresh(&quest_book, &player.inventory, &chunk_manager.current_chunk, &mut story_flags, &mut script_engine);
+            // Objective checks are meant to be read-only, so anything they ask for is dropped
+            script_engine.finish();
+            for update in updates {
+                match update {
+                    QuestUpdate::Started(name) => {
+                        damage_texts.push(DamageText {
+                            position: player.position - vec2(0.0, 30.0),
+                            text: format!("New quest: {}", name),
+                            timer: 3.0,
+                            color: ORANGE,
+                        });
+                    },
+                    QuestUpdate::Completed(quest) => {
+                        player.gold += quest.rewards.gold;
+                        player.inventory.extend(quest.rewards.items.iter().cloned());
+                        let levels = grant_xp(&game_config.player_config, &mut player.level, &mut player.xp, quest.rewards.xp);
+                        if levels > 0 {
+                            player.hp = calculate_stats(&game_config.player_config, player.level, &player.equipment, &game_config.items).max_hp;
+                        }
+                        
+                        damage_texts.push(DamageText {
+                            position: player.position - vec2(0.0, 30.0),
+                            text: format!("Quest complete: {}", quest.name),
+                            timer: 3.0,
+                            color: YELLOW,
+                        });
+                        damage_texts.push(DamageText {
+                            position: player.position,
+                            text: format!("+{} XP  +{} Gold", quest.rewards.xp, quest.rewards.gold),
+                            timer: 3.0,
+                            color: GOLD,
+                        });
+                        if levels > 0 {
+                            damage_texts.push(DamageText {
+                                position: player.position + vec2(0.0, 30.0),
+                                text: format!("Level up! LV {}", player.level),
+                                timer: 3.0,
+                                color: GREEN,
+                            });
+                        }
+                    },
+                }
             }
         }
         
@@ -380,91 +1336,97 @@ async fn main() {
         let camera_offset = vec2(screen_width() / 2.0, screen_height() / 2.0) - camera.position;
         
         // Draw chunks
+        chunk_manager.load_tile_textures();
+        chunk_manager.bake_chunks();
         let default_sprite_renderer = &sprite_renderers["forest"];
         chunk_manager.draw_chunks(camera_offset, default_sprite_renderer);
         
+        // Draw dropped items
+        draw_pickups(&pickups, &game_config.items, camera_offset, get_time() as f32);
+        
         // Draw player
         let player_screen_pos = player.position + camera_offset;
-        let frame_name = if move_dir.length() > 0.0 {
-            if player.animation_timer.sin() > 0.0 { "walk1" } else { "idle" }
-        } else {
-            "idle"
-        };
         
         // Use appropriate sprite renderer
-        let current_chunk_id = chunk_manager.get_chunk_at_position(player.position);
-        let sprite_renderer = if let Some(chunk_id) = &current_chunk_id {
-            if let Some(chunk_config) = world_config.chunks.get(chunk_id) {
-                sprite_renderers.get(&chunk_config.biome).unwrap_or(default_sprite_renderer)
-            } else {
-                default_sprite_renderer
-            }
-        } else {
-            default_sprite_renderer
-        };
+        let sprite_renderer = biome_sprites(&sprite_renderers, &world_config, &player_chunk);
+        let frame_name = player.animator.frame(sprite_renderer, "player");
         
         sprite_renderer.draw_sprite_outlined(
             "player",
             frame_name,
             player_screen_pos,
             2.0,
-            player.facing_left,
+            player.heading,
             BLACK
         );
         
+        // Draw equipped gear on top of the player sprite
+        draw_equipment_overlay(
+            sprite_renderer,
+            &player.equipment,
+            &game_config.items,
+            frame_name,
+            player_screen_pos,
+            2.0,
+            player.heading
+        );
+        
         // Draw attack indicator
         if player.attack_cooldown > 0.4 {
             draw_circle_lines(player_screen_pos.x, player_screen_pos.y, player.radius * 2.0, 3.0, YELLOW);
         }
         
-        // Draw monsters from all loaded chunks
+        // Draw merchants, NPCs and monsters from all loaded chunks
         for chunk_id in &chunk_manager.loaded_chunks {
             if let Some(chunk) = chunk_manager.chunks.get(chunk_id) {
                 // Get sprite renderer for this chunk's biome
-                let chunk_sprite_renderer = if let Some(chunk_config) = world_config.chunks.get(chunk_id) {
-                    sprite_renderers.get(&chunk_config.biome).unwrap_or(default_sprite_renderer)
-                } else {
-                    default_sprite_renderer
-                };
+                let chunk_sprite_renderer = biome_sprites(&sprite_renderers, &world_config, chunk_id);
+                
+                for merchant in chunk.map_data.merchants.iter().flatten() {
+                    let merchant_pos = chunk_manager.chunk_tile_to_world(chunk, merchant.x, merchant.y);
+                    let player_nearby = (merchant_pos - player.position).length() <= INTERACT_RANGE;
+                    draw_merchant(chunk_sprite_renderer, merchant, merchant_pos + camera_offset, player_nearby && !shop_panel.is_open());
+                }
+                
+                for npc in chunk.map_data.npcs.iter().flatten() {
+                    let npc_pos = chunk_manager.chunk_tile_to_world(chunk, npc.x, npc.y);
+                    let player_nearby = (npc_pos - player.position).length() <= INTERACT_RANGE;
+                    draw_npc(chunk_sprite_renderer, npc, npc_pos + camera_offset, player_nearby && !dialogue_box.is_open());
+                }
                 
                 for monster in &chunk.active_monsters {
                     let monster_screen_pos = monster.position + camera_offset;
+                    let sprite_name = monster.data.sprite_name();
                     
-                    if !monster.is_dead {
-                        let sprite_name = match monster.data.name.as_str() {
-                            "Forest Goblin" => "forest_goblin",
-                            "Wild Boar" => "wild_boar",
-                            "Wolf" => "wolf",
-                            "Sand Scorpion" => "sand_scorpion",
-                            "Desert Bandit" => "desert_bandit",
-                            "Dust Devil" => "dust_devil",
-                            "Oasis Guardian" => "oasis_guardian",
-                            _ => "forest_goblin",
-                        };
-                        
+                    // Dead monsters stay on screen until their death animation ends
+                    if !monster.is_dead || monster.animator.is_playing("death") {
                         chunk_sprite_renderer.draw_sprite(
                             sprite_name,
-                            "idle",
+                            monster.animator.frame(chunk_sprite_renderer, sprite_name),
                             monster_screen_pos,
                             2.0,
-                            false
+                            monster.heading
                         );
+                    }
+                    
+                    if !monster.is_dead {
                         
-                        // Health bar
-                        let bar_width = 60.0;
+                        // Health bar under the sprite, at least as wide as it
+                        let bounds = chunk_sprite_renderer.bounds(sprite_name, monster_screen_pos, 2.0)
+                            .unwrap_or(Rect::new(monster_screen_pos.x - 16.0, monster_screen_pos.y - 16.0, 32.0, 32.0));
+                        let bar_width = bounds.w.max(60.0);
                         let bar_height = 6.0;
                         let hp_percent = monster.current_hp as f32 / monster.data.hp as f32;
-                        let sprite_height = 16.0 * 2.0;
                         draw_rectangle(
-                            monster_screen_pos.x - bar_width/2.0,
-                            monster_screen_pos.y + sprite_height/2.0 + 5.0,
+                            bounds.center().x - bar_width/2.0,
+                            bounds.bottom() + 5.0,
                             bar_width,
                             bar_height,
                             DARKGRAY
                         );
                         draw_rectangle(
-                            monster_screen_pos.x - bar_width/2.0,
-                            monster_screen_pos.y + sprite_height/2.0 + 5.0,
+                            bounds.center().x - bar_width/2.0,
+                            bounds.bottom() + 5.0,
                             bar_width * hp_percent,
                             bar_height,
                             GREEN
@@ -474,15 +1436,20 @@ async fn main() {
             }
         }
         
+        // Roofs and treetops go over everything on the ground
+        chunk_manager.draw_overhead(camera_offset, player.position);
+        
         // Draw damage texts
         for dt in &damage_texts {
             let dt_screen_pos = dt.position + camera_offset;
             draw_text(&dt.text, dt_screen_pos.x - 20.0, dt_screen_pos.y, 24.0, dt.color);
         }
         
+        warp_transition.draw();
+        
         // UI
         draw_text("Seamless RPG World", 10.0, 30.0, 30.0, WHITE);
-        draw_text("WASD: Move, SPACE: Attack", 10.0, 60.0, 20.0, WHITE);
+        draw_text("WASD: Move, SPACE: Attack, I: Equipment, J: Quests, E: Talk, F5/F9: Save/Load", 10.0, 60.0, 20.0, WHITE);
         draw_text(&format!("FPS: {}", get_fps()), 10.0, 90.0, 20.0, GREEN);
         
         // Current chunk info
@@ -499,11 +1466,29 @@ async fn main() {
         draw_text(&format!("Loaded Chunks: {}", chunk_manager.loaded_chunks.len()), 10.0, 165.0, 16.0, GRAY);
         
         // Player stats
+        let stats = calculate_stats(&game_config.player_config, player.level, &player.equipment, &game_config.items);
         draw_text("=== Player Stats ===", 10.0, 200.0, 20.0, YELLOW);
-        draw_text(&format!("HP: {}/{}", player.hp, player.max_hp), 10.0, 230.0, 18.0,
+        draw_text(&format!("HP: {}/{}", player.hp, stats.max_hp), 10.0, 230.0, 18.0,
                   if player.hp < 30 { RED } else { WHITE });
-        draw_text(&format!("ATK: {}", player.attack), 10.0, 255.0, 18.0, WHITE);
-        draw_text(&format!("Gold: {}", gold_collected), 10.0, 280.0, 18.0, GOLD);
+        draw_text(&format!("LV: {}  ATK: {}  DEF: {}", player.level, stats.attack, stats.defense), 10.0, 255.0, 18.0, WHITE);
+        draw_text(&format!("Gold: {}", player.gold), 10.0, 280.0, 18.0, GOLD);
+        draw_text(&format!("XP: {}/{}", player.xp, xp_to_next_level(&game_config.player_config, player.level)), 10.0, 305.0, 18.0, SKYBLUE);
+        quest_journal.draw_tracker(&quest_log, &quest_book, &game_config.items, &world_config, 335.0);
+        
+        inventory_panel.draw(&player.inventory, &player.equipment, &game_config.items, &stats);
+        shop_panel.draw(player.gold, &player.inventory, &game_config.items);
+        quest_journal.draw(&quest_log, &quest_book, &game_config.items, &world_config);
+        trigger_tracker.draw(delta);
+        dialogue_box.draw();
+        save_menu.draw(&world_config);
+        
+        if let Some((text, timer)) = &mut save_notice {
+            draw_text(text, 10.0, screen_height() - 20.0, 20.0, SKYBLUE);
+            *timer -= delta;
+            if *timer <= 0.0 {
+                save_notice = None;
+            }
+        }
         
         // Game over screen
         if game_over {
@@ -512,21 +1497,30 @@ async fn main() {
             let text_width = measure_text(text, None, text_size as u16, 1.0).width;
             draw_text(text, screen_width()/2.0 - text_width/2.0, screen_height()/2.0, text_size, RED);
             
-            let restart_text = "Press R to Restart";
+            let restart_text = "Press R to Restart, L to Load";
             let restart_size = 30.0;
             let restart_width = measure_text(restart_text, None, restart_size as u16, 1.0).width;
             draw_text(restart_text, screen_width()/2.0 - restart_width/2.0, screen_height()/2.0 + 50.0, restart_size, WHITE);
             
-            if is_key_pressed(KeyCode::R) {
-                player.hp = player.max_hp;
+            if is_key_pressed(KeyCode::L) && !save_menu.is_open() {
+                save_menu.open(SaveMenuMode::Load);
+            }
+            
+            if is_key_pressed(KeyCode::R) && !save_menu.is_open() {
+                player.hp = stats.max_hp;
                 player.position = spawn_pos;
-                player.facing_left = false;
-                player.animation_timer = 0.0;
-                gold_collected = 0;
+                player.velocity = Vec2::ZERO;
+                player.heading = Heading::default();
+                player.animator = Animator::new();
+                pickups.clear();
                 game_over = false;
             }
         }
         
+        if let Some((_, editor)) = &sprite_editor {
+            editor.draw();
+        }
+        
         next_frame().await
     }
 }
@@ -535,8 +1529,9 @@ pub fn load_monsters(path: &str) -> Vec<Monster> {
     match fs::read_to_string(path) {
         Ok(contents) => {
             match serde_yaml::from_str::<MonsterData>(&contents) {
-                Ok(data) => {
+                Ok(mut data) => {
                     println!("Monster data loaded from {}", path);
+                    data.monsters.retain(|monster| monster_colors_valid(monster, path));
                     data.monsters
                 },
                 Err(e) => {
@@ -552,11 +1547,125 @@ pub fn load_monsters(path: &str) -> Vec<Monster> {
     }
 }
 
+pub fn load_monster_tiers(path: &str) -> Result<HashMap<String, MonsterTier>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            match serde_yaml::from_str::<MonsterTierData>(&contents) {
+                Ok(data) => {
+                    for (name, tier) in &data.tiers {
+                        for (key, color) in &tier.palette {
+                            if let Err(e) = parse_color(color) {
+                                return Err(format!("Tier {} color {}: {}", name, key, e));
+                            }
+                        }
+                    }
+                    Ok(data.tiers)
+                },
+                Err(e) => Err(format!("Tier YAML parsing error: {}", e)),
+            }
+        },
+        Err(e) => Err(format!("Tier file read error: {}", e)),
+    }
+}
+
+// Leaves out, with a message, a monster whose color or palette doesn't parse
+fn monster_colors_valid(monster: &Monster, path: &str) -> bool {
+    let colors = std::iter::once(("color", &monster.color))
+        .chain(monster.palette.iter().map(|(key, color)| (key.as_str(), color)));
+    for (key, color) in colors {
+        if let Err(e) = parse_color(color) {
+            println!("Monster {} in {} {}: {}", monster.name, path, key, e);
+            return false;
+        }
+    }
+    true
+}
+
+// A recolored copy of a sprite: its name, the base sprite and the colors swapped
+type Recolor = (String, String, HashMap<String, String>);
+
+// Names a recolored copy of the sprite for every monster with its own
+// palette and points the monster at it
+fn monster_recolors(monsters: &mut [Monster], sprite_files: &HashMap<String, (String, SpriteData)>) -> Vec<Recolor> {
+    let mut recolors = Vec::new();
+    for monster in monsters {
+        if monster.palette.is_empty() {
+            continue;
+        }
+        let base = monster.sprite_name().to_string();
+        if !sprite_files.values().any(|(_, sprite_data)| sprite_data.sprites.contains_key(&base)) {
+            continue;
+        }
+        let recolor = match &monster.tier {
+            Some(tier) => format!("{}@{}@{}", base, monster.name, tier),
+            None => format!("{}@{}", base, monster.name),
+        };
+        monster.sprite = Some(recolor.clone());
+        recolors.push((recolor, base, monster.palette.clone()));
+    }
+    recolors
+}
+
+// A renderer for a sprite file with the monster recolors added to it
+fn bake_sprites(sprite_data: &SpriteData, recolors: &[Recolor]) -> SpriteRenderer {
+    let mut sprite_data = sprite_data.clone();
+    for (recolor, base, palette) in recolors {
+        sprite_data.add_recolor(recolor, base, palette);
+    }
+    SpriteRenderer::new(sprite_data)
+}
+
+fn monster_sprite_name(monster_name: &str) -> &'static str {
+    match monster_name {
+        "Forest Goblin" => "forest_goblin",
+        "Wild Boar" => "wild_boar",
+        "Wolf" => "wolf",
+        "Sand Scorpion" => "sand_scorpion",
+        "Desert Bandit" => "desert_bandit",
+        "Dust Devil" => "dust_devil",
+        "Oasis Guardian" => "oasis_guardian",
+        _ => "forest_goblin",
+    }
+}
+
+// The sprites of the biome a chunk belongs to, the forest ones otherwise
+fn biome_sprites<'a>(sprite_renderers: &'a HashMap<String, SpriteRenderer>, world_config: &WorldConfig, chunk_id: &str) -> &'a SpriteRenderer {
+    world_config.chunks.get(chunk_id)
+        .and_then(|chunk_config| sprite_renderers.get(&chunk_config.biome))
+        .unwrap_or(&sprite_renderers["forest"])
+}
+
 pub fn load_map(path: &str) -> Result<MapData, String> {
+    let mut data = if tiled_system::is_tiled_map(path) {
+        tiled_system::load_tiled_map(path)?
+    } else {
+        read_map(path)?
+    };
+    for (key, tile_type) in &data.tile_types {
+        if let Err(e) = parse_color(&tile_type.color) {
+            return Err(format!("Map {} tile {}: {}", path, key, e));
+        }
+    }
+    data.grid = TileGrid::compile(&data)?;
+    Ok(data)
+}
+
+fn read_map(path: &str) -> Result<MapData, String> {
     match fs::read_to_string(path) {
         Ok(contents) => {
             match serde_yaml::from_str::<MapData>(&contents) {
-                Ok(data) => Ok(data),
+                Ok(mut data) => {
+                    if !data.layout.is_empty() && !data.layers.ground.is_empty() {
+                        return Err(format!("Map {} has both a layout and a ground layer", path));
+                    }
+                    if data.layers.ground.is_empty() {
+                        data.layers.ground = std::mem::take(&mut data.layout);
+                    }
+                    if data.layers.ground.is_empty() {
+                        return Err(format!("Map {} has no layout", path));
+                    }
+                    Ok(data)
+                },
                 Err(e) => Err(format!("Map YAML parsing error: {}", e)),
             }
         },
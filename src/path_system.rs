@@ -0,0 +1,89 @@
+use macroquad::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use crate::chunk_system::ChunkManager;
+
+// How far from the start tile a search may wander, in tiles
+const MAX_SEARCH_RADIUS: i32 = 16;
+// Costs are kept as integers in the heap, scaled by this much
+const COST_SCALE: f32 = 1000.0;
+// Cheapest possible step, so the distance estimate never overshoots
+const MIN_STEP_COST: f32 = 0.5;
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+];
+
+// A* over the world tiles of the loaded chunks. Each step costs its length
+// times the movement cost of the tile stepped onto, so slow or harmful ground
+// is walked around when there is a reasonable detour. Returns the centers of
+// the tiles to walk through, not counting the start tile.
+pub fn find_path(chunk_manager: &ChunkManager, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+    let tile_size = chunk_manager.world_config.world_info.tile_size;
+    let to_tile = |pos: Vec2| ((pos.x / tile_size).floor() as i32, (pos.y / tile_size).floor() as i32);
+    let center = |(x, y): (i32, i32)| vec2((x as f32 + 0.5) * tile_size, (y as f32 + 0.5) * tile_size);
+    let estimate = |(x, y): (i32, i32), (gx, gy): (i32, i32)| {
+        let dx = (x - gx).abs() as f32;
+        let dy = (y - gy).abs() as f32;
+        (dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)) * MIN_STEP_COST
+    };
+
+    let start = to_tile(from);
+    let goal = to_tile(to);
+    if start == goal {
+        return Some(Vec::new());
+    }
+    chunk_manager.movement_cost(center(goal))?;
+
+    let mut open = BinaryHeap::new();
+    let mut best: HashMap<(i32, i32), f32> = HashMap::new();
+    let mut came_from: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    best.insert(start, 0.0);
+    open.push(Reverse(((estimate(start, goal) * COST_SCALE) as u32, start)));
+
+    while let Some(Reverse((_, tile))) = open.pop() {
+        if tile == goal {
+            let mut path = vec![center(tile)];
+            let mut current = tile;
+            while let Some(&previous) = came_from.get(&current) {
+                if previous == start {
+                    break;
+                }
+                path.push(center(previous));
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let cost_here = best[&tile];
+        for (dx, dy) in NEIGHBORS {
+            let next = (tile.0 + dx, tile.1 + dy);
+            if (next.0 - start.0).abs() > MAX_SEARCH_RADIUS || (next.1 - start.1).abs() > MAX_SEARCH_RADIUS {
+                continue;
+            }
+            let step_cost = match chunk_manager.movement_cost(center(next)) {
+                Some(cost) => cost,
+                None => continue,
+            };
+            // No cutting corners past walls
+            if dx != 0 && dy != 0 &&
+               (chunk_manager.movement_cost(center((tile.0 + dx, tile.1))).is_none() ||
+                chunk_manager.movement_cost(center((tile.0, tile.1 + dy))).is_none()) {
+                continue;
+            }
+
+            let length = if dx != 0 && dy != 0 { std::f32::consts::SQRT_2 } else { 1.0 };
+            let cost = cost_here + length * step_cost;
+            if best.get(&next).is_none_or(|&known| cost < known) {
+                best.insert(next, cost);
+                came_from.insert(next, tile);
+                let priority = cost + estimate(next, goal);
+                open.push(Reverse(((priority * COST_SCALE) as u32, next)));
+            }
+        }
+    }
+
+    None
+}
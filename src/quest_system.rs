@@ -0,0 +1,366 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use crate::biome_system::{ItemConfig, PlayerConfig};
+use crate::chunk_system::WorldConfig;
+use crate::script_system::ScriptEngine;
+use crate::Monster;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuestBook {
+    pub quests: Vec<QuestData>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuestData {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    // Story flag that makes the quest available; no flag means it starts right away
+    #[serde(default)]
+    pub start_flag: Option<String>,
+    // Completed one after another, in order
+    pub objectives: Vec<QuestObjective>,
+    #[serde(default)]
+    pub rewards: QuestRewards,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestObjective {
+    Kill { monster: String, count: u32 },
+    Collect { item: String, count: u32 },
+    ReachChunk(String),
+    TalkTo(String),
+    // Done once the script's `fn is_complete()` returns true
+    Script { file: String, description: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct QuestRewards {
+    #[serde(default)]
+    pub gold: i32,
+    #[serde(default)]
+    pub xp: i32,
+    #[serde(default)]
+    pub items: Vec<String>,
+}
+
+impl QuestBook {
+    pub fn get(&self, id: &str) -> Option<&QuestData> {
+        self.quests.iter().find(|quest| quest.id == id)
+    }
+}
+
+impl QuestObjective {
+    pub fn required(&self) -> u32 {
+        match self {
+            QuestObjective::Kill { count, .. } | QuestObjective::Collect { count, .. } => (*count).max(1),
+            QuestObjective::ReachChunk(_) | QuestObjective::TalkTo(_) | QuestObjective::Script { .. } => 1,
+        }
+    }
+
+    pub fn describe(&self, items: &HashMap<String, ItemConfig>, world_config: &WorldConfig) -> String {
+        match self {
+            QuestObjective::Kill { monster, .. } => format!("Defeat {}", monster),
+            QuestObjective::Collect { item, .. } => {
+                let name = items.get(item).map(|item| item.name.as_str()).unwrap_or(item);
+                format!("Collect {}", name)
+            },
+            QuestObjective::ReachChunk(chunk) => {
+                let name = world_config.chunks.get(chunk).map(|chunk| chunk.name.as_str()).unwrap_or(chunk);
+                format!("Travel to {}", name)
+            },
+            QuestObjective::TalkTo(npc) => format!("Talk to {}", npc),
+            QuestObjective::Script { description, .. } => description.clone(),
+        }
+    }
+}
+
+// Story flag set when a quest is finished, so dialogue can react to it
+pub fn completion_flag(quest_id: &str) -> String {
+    format!("{}_done", quest_id)
+}
+
+pub fn load_quests(path: &str) -> Result<QuestBook, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            match serde_yaml::from_str::<QuestBook>(&contents) {
+                Ok(book) => Ok(book),
+                Err(e) => Err(format!("Quest YAML parsing error: {}", e)),
+            }
+        },
+        Err(e) => Err(format!("Quest file read error: {}", e)),
+    }
+}
+
+// Warns about objectives and rewards that can never be satisfied or handed out
+pub fn validate_quests(
+    book: &QuestBook,
+    monsters: &[Monster],
+    world_config: &WorldConfig,
+    items: &HashMap<String, ItemConfig>,
+    scripts: &mut ScriptEngine,
+) {
+    for quest in &book.quests {
+        for objective in &quest.objectives {
+            let known = match objective {
+                QuestObjective::Kill { monster, .. } => monsters.iter().any(|m| &m.name == monster),
+                QuestObjective::Collect { item, .. } => items.contains_key(item),
+                QuestObjective::ReachChunk(chunk) => world_config.chunks.contains_key(chunk),
+                QuestObjective::TalkTo(_) => true,
+                QuestObjective::Script { file, .. } => match scripts.load(file) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        println!("Script error: {}", e);
+                        true
+                    },
+                },
+            };
+            if !known {
+                println!("Quest {} has an objective that refers to nothing: {:?}", quest.id, objective);
+            }
+        }
+        for item in &quest.rewards.items {
+            if !items.contains_key(item) {
+                println!("Unknown reward item '{}' in quest {}", item, quest.id);
+            }
+        }
+    }
+}
+
+pub enum QuestEvent<'a> {
+    Killed(&'a str),
+    TalkedTo(&'a str),
+}
+
+pub enum QuestUpdate {
+    Started(String),
+    Completed(QuestData),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct QuestLog {
+    pub active: Vec<QuestProgress>,
+    pub completed: Vec<String>,
+}
+
+// Progress on the current objective of one active quest
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuestProgress {
+    pub id: String,
+    pub objective: usize,
+    pub count: u32,
+}
+
+impl QuestLog {
+    pub fn record(&mut self, event: QuestEvent, book: &QuestBook) {
+        for progress in &mut self.active {
+            let objective = match book.get(&progress.id).and_then(|quest| quest.objectives.get(progress.objective)) {
+                Some(objective) => objective,
+                None => continue,
+            };
+
+            let matches = match (objective, &event) {
+                (QuestObjective::Kill { monster, .. }, QuestEvent::Killed(name)) => monster == name,
+                (QuestObjective::TalkTo(npc), QuestEvent::TalkedTo(name)) => npc == name,
+                _ => false,
+            };
+            if matches {
+                progress.count += 1;
+            }
+        }
+    }
+
+    // Starts newly available quests and advances objectives that are met by the
+    // current game state. Finished quests set their completion flag.
+    pub fn refresh(
+        &mut self,
+        book: &QuestBook,
+        inventory: &[String],
+        current_chunk: &str,
+        flags: &mut HashSet<String>,
+        scripts: &mut ScriptEngine,
+    ) -> Vec<QuestUpdate> {
+        let mut updates = Vec::new();
+
+        for quest in &book.quests {
+            let known = self.completed.contains(&quest.id) || self.active.iter().any(|progress| progress.id == quest.id);
+            let available = quest.start_flag.as_ref().is_none_or(|flag| flags.contains(flag));
+            if !known && available {
+                self.active.push(QuestProgress { id: quest.id.clone(), objective: 0, count: 0 });
+                updates.push(QuestUpdate::Started(quest.name.clone()));
+            }
+        }
+
+        let mut finished = Vec::new();
+        for progress in &mut self.active {
+            let quest = match book.get(&progress.id) {
+                Some(quest) => quest,
+                None => continue,
+            };
+
+            while let Some(objective) = quest.objectives.get(progress.objective) {
+                match objective {
+                    QuestObjective::Collect { item, .. } => {
+                        progress.count = inventory.iter().filter(|id| *id == item).count() as u32;
+                    },
+                    QuestObjective::ReachChunk(chunk) if chunk == current_chunk => {
+                        progress.count = 1;
+                    },
+                    QuestObjective::Script { file, .. } if scripts.check(file) => {
+                        progress.count = 1;
+                    },
+                    _ => {},
+                }
+
+                if progress.count < objective.required() {
+                    break;
+                }
+                progress.objective += 1;
+                progress.count = 0;
+            }
+
+            if progress.objective >= quest.objectives.len() {
+                finished.push(quest.id.clone());
+                flags.insert(completion_flag(&quest.id));
+                updates.push(QuestUpdate::Completed(quest.clone()));
+            }
+        }
+
+        self.active.retain(|progress| !finished.contains(&progress.id));
+        self.completed.extend(finished);
+        updates
+    }
+}
+
+pub fn xp_to_next_level(config: &PlayerConfig, level: i32) -> i32 {
+    config.xp_per_level * level
+}
+
+// Adds experience and returns how many levels were gained
+pub fn grant_xp(config: &PlayerConfig, level: &mut i32, xp: &mut i32, amount: i32) -> i32 {
+    let mut levels_gained = 0;
+    *xp += amount;
+    while *xp >= xp_to_next_level(config, *level) {
+        *xp -= xp_to_next_level(config, *level);
+        *level += 1;
+        levels_gained += 1;
+    }
+    levels_gained
+}
+
+pub struct QuestJournal {
+    pub is_open: bool,
+}
+
+impl QuestJournal {
+    pub fn new() -> Self {
+        QuestJournal {
+            is_open: false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::J) {
+            self.is_open = !self.is_open;
+        }
+    }
+
+    // One-line reminder of the first active quest, shown under the player stats
+    pub fn draw_tracker(
+        &self,
+        log: &QuestLog,
+        book: &QuestBook,
+        items: &HashMap<String, ItemConfig>,
+        world_config: &WorldConfig,
+        y: f32,
+    ) {
+        let progress = match log.active.first() {
+            Some(progress) => progress,
+            None => return,
+        };
+        let quest = match book.get(&progress.id) {
+            Some(quest) => quest,
+            None => return,
+        };
+
+        draw_text(&format!("Quest: {}", quest.name), 10.0, y, 18.0, ORANGE);
+        if let Some(objective) = quest.objectives.get(progress.objective) {
+            draw_text(&objective_line(objective, progress.count, items, world_config), 20.0, y + 20.0, 16.0, LIGHTGRAY);
+        }
+    }
+
+    pub fn draw(
+        &self,
+        log: &QuestLog,
+        book: &QuestBook,
+        items: &HashMap<String, ItemConfig>,
+        world_config: &WorldConfig,
+    ) {
+        if !self.is_open {
+            return;
+        }
+
+        let active: Vec<(&QuestData, &QuestProgress)> = log.active.iter()
+            .filter_map(|progress| book.get(&progress.id).map(|quest| (quest, progress)))
+            .collect();
+        let objective_rows: usize = active.iter().map(|(quest, progress)| (progress.objective + 1).min(quest.objectives.len())).sum();
+
+        let panel_width = 420.0;
+        let panel_height = 110.0 + active.len() as f32 * 48.0 + objective_rows as f32 * 20.0 + log.completed.len() as f32 * 20.0;
+        let panel_x = screen_width() - panel_width - 20.0;
+        let panel_y = 20.0;
+
+        draw_rectangle(panel_x, panel_y, panel_width, panel_height, Color::new(0.0, 0.0, 0.0, 0.85));
+        draw_rectangle_lines(panel_x, panel_y, panel_width, panel_height, 2.0, ORANGE);
+        draw_text("=== Quest Log ===", panel_x + 10.0, panel_y + 25.0, 20.0, YELLOW);
+
+        let mut y = panel_y + 52.0;
+        if active.is_empty() {
+            draw_text("(no active quests)", panel_x + 20.0, y, 18.0, GRAY);
+            y += 22.0;
+        }
+        for (quest, progress) in &active {
+            draw_text(&quest.name, panel_x + 20.0, y, 18.0, ORANGE);
+            draw_text(&quest.description, panel_x + 20.0, y + 20.0, 14.0, LIGHTGRAY);
+            y += 44.0;
+
+            // Finished objectives are ticked off; later ones stay hidden until reached
+            for (i, objective) in quest.objectives.iter().enumerate().take(progress.objective + 1) {
+                let (line, color) = if i < progress.objective {
+                    (format!("[x] {}", objective.describe(items, world_config)), GRAY)
+                } else {
+                    (format!("[ ] {}", objective_line(objective, progress.count, items, world_config)), WHITE)
+                };
+                draw_text(&line, panel_x + 30.0, y, 16.0, color);
+                y += 20.0;
+            }
+            y += 4.0;
+        }
+
+        draw_text("Completed", panel_x + 10.0, y + 4.0, 18.0, YELLOW);
+        y += 26.0;
+        for id in &log.completed {
+            let name = book.get(id).map(|quest| quest.name.as_str()).unwrap_or(id);
+            draw_text(name, panel_x + 20.0, y, 16.0, GRAY);
+            y += 20.0;
+        }
+
+        draw_text("J: Close", panel_x + 10.0, panel_y + panel_height - 8.0, 14.0, GRAY);
+    }
+}
+
+fn objective_line(
+    objective: &QuestObjective,
+    count: u32,
+    items: &HashMap<String, ItemConfig>,
+    world_config: &WorldConfig,
+) -> String {
+    let text = objective.describe(items, world_config);
+    if objective.required() > 1 {
+        format!("{} ({}/{})", text, count.min(objective.required()), objective.required())
+    } else {
+        text
+    }
+}
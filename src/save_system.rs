@@ -0,0 +1,380 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use serde_json::Value;
+use crate::chunk_system::WorldConfig;
+use crate::equipment_system::Equipment;
+use crate::quest_system::QuestLog;
+
+// Bump SAVE_VERSION for every change to the structs below, add a step to
+// MIGRATIONS and drop a save of the old version into tests/saves/.
+pub const SAVE_VERSION: u32 = 5;
+pub const SAVE_DIR: &str = "saves";
+pub const AUTOSAVE_SLOT: &str = "autosave";
+pub const SAVE_SLOTS: [&str; 4] = [AUTOSAVE_SLOT, "slot1", "slot2", "slot3"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveData {
+    pub version: u32,
+    pub saved_at: u64,
+    pub world_clock: f32,
+    pub story_flags: Vec<String>,
+    pub quests: QuestLog,
+    pub player: PlayerSave,
+    pub chunks: HashMap<String, ChunkState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerSave {
+    pub position: [f32; 2],
+    pub chunk: String,
+    // Set when saved inside an interior: the grid chunk behind the door
+    pub outdoor_chunk: Option<String>,
+    pub hp: i32,
+    pub level: i32,
+    pub xp: i32,
+    pub gold: i32,
+    pub inventory: Vec<String>,
+    pub equipment: Equipment,
+}
+
+// What changed in a chunk since it was loaded from its map file.
+// Monsters are stored in spawn order so they line up with monster_spawns;
+// monsters spawned by map events aren't in the map file and are kept apart.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChunkState {
+    pub monsters: Vec<MonsterState>,
+    pub spawned: Vec<SpawnedMonsterState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MonsterState {
+    pub position: [f32; 2],
+    pub current_hp: i32,
+    pub is_dead: bool,
+    pub respawn_timer: f32,
+}
+
+// Spawned monsters never respawn, so only living ones are kept
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpawnedMonsterState {
+    pub monster_type: String,
+    pub position: [f32; 2],
+    pub current_hp: i32,
+}
+
+// MIGRATIONS[n] upgrades a version n + 1 save to version n + 2 in place
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+const MIGRATIONS: &[Migration] = &[
+    v1_to_v2,
+    v2_to_v3,
+    v3_to_v4,
+    v4_to_v5,
+];
+
+const _: () = assert!(MIGRATIONS.len() as u32 == SAVE_VERSION - 1);
+
+// v2: dialogue story flags
+fn v1_to_v2(save: &mut Value) -> Result<(), String> {
+    match save.as_object_mut() {
+        Some(object) => {
+            object.insert("story_flags".to_string(), Value::Array(Vec::new()));
+            Ok(())
+        },
+        None => Err("save is not a JSON object".to_string()),
+    }
+}
+
+// v3: quest log and player experience
+fn v2_to_v3(save: &mut Value) -> Result<(), String> {
+    let object = match save.as_object_mut() {
+        Some(object) => object,
+        None => return Err("save is not a JSON object".to_string()),
+    };
+    object.insert("quests".to_string(), serde_json::json!({ "active": [], "completed": [] }));
+
+    match object.get_mut("player").and_then(Value::as_object_mut) {
+        Some(player) => {
+            player.insert("xp".to_string(), Value::from(0));
+            Ok(())
+        },
+        None => Err("save has no player".to_string()),
+    }
+}
+
+// v4: interiors outside the chunk grid
+fn v3_to_v4(save: &mut Value) -> Result<(), String> {
+    match save.get_mut("player").and_then(Value::as_object_mut) {
+        Some(player) => {
+            player.insert("outdoor_chunk".to_string(), Value::Null);
+            Ok(())
+        },
+        None => Err("save has no player".to_string()),
+    }
+}
+
+// v5: monsters spawned by map events
+fn v4_to_v5(save: &mut Value) -> Result<(), String> {
+    let chunks = match save.get_mut("chunks").and_then(Value::as_object_mut) {
+        Some(chunks) => chunks,
+        None => return Err("save has no chunks".to_string()),
+    };
+
+    for (chunk_id, chunk) in chunks.iter_mut() {
+        match chunk.as_object_mut() {
+            Some(chunk) => {
+                chunk.insert("spawned".to_string(), Value::Array(Vec::new()));
+            },
+            None => return Err(format!("chunk {} is not a JSON object", chunk_id)),
+        }
+    }
+    Ok(())
+}
+
+// Walks an older save up the migration chain one version at a time
+pub fn migrate_save(mut save: Value) -> Result<Value, String> {
+    let version = match save.get("version").and_then(Value::as_u64) {
+        Some(version) => version as u32,
+        None => return Err("Save file has no version".to_string()),
+    };
+
+    if version == 0 || version > SAVE_VERSION {
+        return Err(format!("Unsupported save version {} (newest known is {})", version, SAVE_VERSION));
+    }
+
+    for from_version in version..SAVE_VERSION {
+        if let Err(e) = MIGRATIONS[from_version as usize - 1](&mut save) {
+            return Err(format!("Migrating save from version {} failed: {}", from_version, e));
+        }
+        save["version"] = Value::from(from_version + 1);
+    }
+
+    Ok(save)
+}
+
+pub fn parse_save(contents: &str) -> Result<SaveData, String> {
+    let raw = match serde_json::from_str::<Value>(contents) {
+        Ok(raw) => raw,
+        Err(e) => return Err(format!("JSON parsing error: {}", e)),
+    };
+
+    match serde_json::from_value::<SaveData>(migrate_save(raw)?) {
+        Ok(save) => Ok(save),
+        Err(e) => Err(format!("Save data error: {}", e)),
+    }
+}
+
+fn slot_path(slot: &str) -> String {
+    format!("{}/{}.json", SAVE_DIR, slot)
+}
+
+pub fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn write_save(slot: &str, save: &SaveData) -> Result<(), String> {
+    if let Err(e) = fs::create_dir_all(SAVE_DIR) {
+        return Err(format!("Save directory error: {}", e));
+    }
+
+    match serde_json::to_string_pretty(save) {
+        Ok(contents) => match fs::write(slot_path(slot), contents) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!("File write error: {}", e)),
+        },
+        Err(e) => Err(format!("JSON serialize error: {}", e)),
+    }
+}
+
+pub fn read_save(slot: &str) -> Result<SaveData, String> {
+    match fs::read_to_string(slot_path(slot)) {
+        Ok(contents) => parse_save(&contents),
+        Err(e) => Err(format!("File read error: {}", e)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SaveMenuMode {
+    Save,
+    Load,
+}
+
+pub enum SaveMenuAction {
+    Save(String),
+    Load(String),
+}
+
+pub struct SaveMenu {
+    mode: Option<SaveMenuMode>,
+    selected: usize,
+    // None when the slot has never been written
+    slots: Vec<(String, Option<Result<SaveData, String>>)>,
+}
+
+impl SaveMenu {
+    pub fn new() -> Self {
+        SaveMenu {
+            mode: None,
+            selected: 0,
+            slots: Vec::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    pub fn open(&mut self, mode: SaveMenuMode) {
+        // The autosave slot can be loaded but not written by hand
+        self.slots = SAVE_SLOTS.iter()
+            .filter(|slot| mode == SaveMenuMode::Load || **slot != AUTOSAVE_SLOT)
+            .map(|slot| {
+                let existing = if std::path::Path::new(&slot_path(slot)).exists() {
+                    Some(read_save(slot))
+                } else {
+                    None
+                };
+                (slot.to_string(), existing)
+            })
+            .collect();
+        self.mode = Some(mode);
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.mode = None;
+    }
+
+    pub fn update(&mut self) -> Option<SaveMenuAction> {
+        let mode = self.mode?;
+
+        if is_key_pressed(KeyCode::Escape) {
+            self.close();
+            return None;
+        }
+        if is_key_pressed(KeyCode::Up) && self.selected > 0 {
+            self.selected -= 1;
+        }
+        if is_key_pressed(KeyCode::Down) && self.selected + 1 < self.slots.len() {
+            self.selected += 1;
+        }
+
+        if !is_key_pressed(KeyCode::Enter) {
+            return None;
+        }
+
+        let (slot, existing) = &self.slots[self.selected];
+        match mode {
+            SaveMenuMode::Save => Some(SaveMenuAction::Save(slot.clone())),
+            SaveMenuMode::Load if matches!(existing, Some(Ok(_))) => Some(SaveMenuAction::Load(slot.clone())),
+            SaveMenuMode::Load => None,
+        }
+    }
+
+    pub fn draw(&self, world_config: &WorldConfig) {
+        let mode = match self.mode {
+            Some(mode) => mode,
+            None => return,
+        };
+
+        let panel_width = 460.0;
+        let panel_height = 90.0 + self.slots.len() as f32 * 44.0;
+        let panel_x = screen_width() / 2.0 - panel_width / 2.0;
+        let panel_y = screen_height() / 2.0 - panel_height / 2.0;
+
+        draw_rectangle(panel_x, panel_y, panel_width, panel_height, Color::new(0.0, 0.0, 0.0, 0.9));
+        draw_rectangle_lines(panel_x, panel_y, panel_width, panel_height, 2.0, WHITE);
+        let title = if mode == SaveMenuMode::Save { "=== Save Game ===" } else { "=== Load Game ===" };
+        draw_text(title, panel_x + 10.0, panel_y + 25.0, 20.0, YELLOW);
+
+        let mut y = panel_y + 55.0;
+        for (i, (slot, existing)) in self.slots.iter().enumerate() {
+            let color = if i == self.selected { YELLOW } else { WHITE };
+            draw_text(slot, panel_x + 20.0, y, 18.0, color);
+
+            let (summary, summary_color) = match existing {
+                Some(Ok(save)) => {
+                    let location = world_config.chunks.get(&save.player.chunk)
+                        .map(|chunk| chunk.name.clone())
+                        .unwrap_or_else(|| save.player.chunk.clone());
+                    let minutes = (save.world_clock / 60.0) as u32;
+                    (format!("Lv {}  {} G  {}  ({}m {:02}s played)",
+                             save.player.level, save.player.gold, location,
+                             minutes, save.world_clock as u32 % 60), LIGHTGRAY)
+                },
+                Some(Err(e)) => (e.clone(), RED),
+                None => ("(empty)".to_string(), GRAY),
+            };
+            draw_text(&summary, panel_x + 40.0, y + 20.0, 16.0, summary_color);
+            y += 44.0;
+        }
+
+        draw_text("UP/DOWN: Select, ENTER: Confirm, ESC: Close", panel_x + 10.0, y + 5.0, 14.0, GRAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/saves");
+
+    fn corpus() -> Vec<(String, String)> {
+        let mut files: Vec<(String, String)> = fs::read_dir(CORPUS_DIR)
+            .expect("save corpus directory")
+            .map(|entry| entry.expect("corpus entry").path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, fs::read_to_string(&path).expect("readable corpus file"))
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn every_historical_save_still_loads() {
+        let files = corpus();
+        assert!(!files.is_empty(), "no saves found in {}", CORPUS_DIR);
+
+        for (name, contents) in files {
+            let save = parse_save(&contents).unwrap_or_else(|e| panic!("{} failed to load: {}", name, e));
+            assert_eq!(save.version, SAVE_VERSION, "{} was not migrated to the current version", name);
+        }
+    }
+
+    #[test]
+    fn corpus_covers_every_version() {
+        for version in 1..=SAVE_VERSION {
+            let prefix = format!("v{}_", version);
+            assert!(
+                corpus().iter().any(|(name, _)| name.starts_with(&prefix)),
+                "tests/saves has no {}*.json save",
+                prefix
+            );
+        }
+    }
+
+    #[test]
+    fn migrated_save_round_trips() {
+        for (name, contents) in corpus() {
+            let save = parse_save(&contents).unwrap();
+            let written = serde_json::to_string(&save).unwrap();
+            assert!(parse_save(&written).is_ok(), "{} did not survive a rewrite", name);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        assert!(parse_save(r#"{"world_clock": 0.0}"#).is_err());
+        assert!(parse_save(r#"{"version": 0}"#).is_err());
+        let future = format!(r#"{{"version": {}}}"#, SAVE_VERSION + 1);
+        assert!(parse_save(&future).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use macroquad::prelude::*;
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Position, Scope, AST, FLOAT, INT};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use crate::biome_system::ItemConfig;
+use crate::trigger_system::TriggerAction;
+
+// Scripts get a copy of the game state they may look at and hand back a list
+// of commands; they never touch game structs directly.
+#[derive(Clone, Default)]
+pub struct ScriptState {
+    pub player_pos: Vec2,
+    pub hp: i32,
+    pub level: i32,
+    pub gold: i32,
+    pub inventory: Vec<String>,
+    pub flags: HashSet<String>,
+    pub chunk: String,
+    pub world_time: f32,
+    pub commands: Vec<ScriptCommand>,
+}
+
+#[derive(Clone)]
+pub enum ScriptCommand {
+    Action(TriggerAction),
+    GiveItem(String),
+    TakeItem(String),
+    GiveGold(i32),
+    Heal(i32),
+}
+
+// Caps that keep a runaway script from freezing the game
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 4096;
+const MAX_COLLECTION_SIZE: usize = 1024;
+
+pub struct ScriptEngine {
+    engine: Engine,
+    state: Rc<RefCell<ScriptState>>,
+    // Shared so running a script doesn't copy its whole AST
+    scripts: HashMap<String, Result<Rc<AST>, String>>,
+    // Broken scripts are reported once instead of every frame
+    reported: HashSet<String>,
+}
+
+impl ScriptEngine {
+    pub fn new(items: &HashMap<String, ItemConfig>) -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        engine.set_max_string_size(MAX_STRING_SIZE);
+        engine.set_max_array_size(MAX_COLLECTION_SIZE);
+        engine.set_max_map_size(MAX_COLLECTION_SIZE);
+        engine.set_strict_variables(true);
+        // No file access from scripts
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.on_print(|text| println!("[script] {}", text));
+
+        let state = Rc::new(RefCell::new(ScriptState::default()));
+        register_api(&mut engine, &state, items);
+
+        ScriptEngine {
+            engine,
+            state,
+            scripts: HashMap::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    // Compiles a script (once) so errors can be reported at load time
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        if !self.scripts.contains_key(path) {
+            let compiled = match std::fs::read_to_string(path) {
+                Ok(source) => match self.engine.compile(&source) {
+                    Ok(ast) => Ok(Rc::new(ast)),
+                    Err(e) => Err(format!("{}:{}: {}", path, line_of(e.position()), e.err_type())),
+                },
+                Err(e) => Err(format!("Script file read error: {}: {}", path, e)),
+            };
+            self.scripts.insert(path.to_string(), compiled);
+        }
+
+        match &self.scripts[path] {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.clone()),
+        }
+    }
+
+    // Hands the scripts a fresh view of the game; call finish() to collect commands
+    pub fn begin(&mut self, state: ScriptState) {
+        *self.state.borrow_mut() = state;
+    }
+
+    pub fn finish(&mut self) -> Vec<ScriptCommand> {
+        std::mem::take(&mut self.state.borrow_mut().commands)
+    }
+
+    // Runs a script's top-level statements, as used by map triggers
+    pub fn run(&mut self, path: &str) {
+        if let Some(ast) = self.compiled(path) {
+            let result = self.engine.run_ast_with_scope(&mut Scope::new(), &ast);
+            if let Err(e) = result {
+                self.report(path, *e);
+            }
+        }
+    }
+
+    // Calls `fn is_complete()` of a quest objective script
+    pub fn check(&mut self, path: &str) -> bool {
+        let ast = match self.compiled(path) {
+            Some(ast) => ast,
+            None => return false,
+        };
+        match self.engine.call_fn::<bool>(&mut Scope::new(), &ast, "is_complete", ()) {
+            Ok(done) => done,
+            Err(e) => {
+                self.report(path, *e);
+                false
+            },
+        }
+    }
+
+    // Calls `fn update(monster, dt)` of a behavior script. The script returns
+    // #{ dx, dy } to move that way, or nothing to fall back to the built-in AI.
+    pub fn monster_update(&mut self, path: &str, monster: Map, delta: f32) -> Option<Vec2> {
+        let ast = self.compiled(path)?;
+        match self.engine.call_fn::<Dynamic>(&mut Scope::new(), &ast, "update", (monster, delta as FLOAT)) {
+            Ok(result) => {
+                let map = result.try_cast::<Map>()?;
+                let dx = map.get("dx").and_then(|v| v.as_float().ok())?;
+                let dy = map.get("dy").and_then(|v| v.as_float().ok())?;
+                Some(vec2(dx as f32, dy as f32))
+            },
+            Err(e) => {
+                self.report(path, *e);
+                None
+            },
+        }
+    }
+
+    fn compiled(&mut self, path: &str) -> Option<Rc<AST>> {
+        match self.load(path) {
+            Ok(()) => self.scripts[path].as_ref().ok().cloned(),
+            Err(e) => {
+                if self.reported.insert(path.to_string()) {
+                    println!("Script error: {}", e);
+                }
+                None
+            },
+        }
+    }
+
+    fn report(&mut self, path: &str, mut error: EvalAltResult) {
+        // Point at the line inside the function that failed, not the call site
+        while let EvalAltResult::ErrorInFunctionCall(_, _, inner, _) = error {
+            error = *inner;
+        }
+        if self.reported.insert(path.to_string()) {
+            println!("Script error: {}:{}: {}", path, line_of(error.position()), error);
+        }
+    }
+}
+
+fn line_of(position: Position) -> usize {
+    position.line().unwrap_or(0)
+}
+
+pub fn monster_map(name: &str, position: Vec2, hp: i32, max_hp: i32, speed: f32) -> Map {
+    let mut map = Map::new();
+    map.insert("name".into(), name.into());
+    map.insert("x".into(), (position.x as FLOAT).into());
+    map.insert("y".into(), (position.y as FLOAT).into());
+    map.insert("hp".into(), (hp as INT).into());
+    map.insert("max_hp".into(), (max_hp as INT).into());
+    map.insert("speed".into(), (speed as FLOAT).into());
+    map
+}
+
+fn register_api(engine: &mut Engine, state: &Rc<RefCell<ScriptState>>, items: &HashMap<String, ItemConfig>) {
+    // Player, read-only
+    let s = state.clone();
+    engine.register_fn("player_x", move || s.borrow().player_pos.x as FLOAT);
+    let s = state.clone();
+    engine.register_fn("player_y", move || s.borrow().player_pos.y as FLOAT);
+    let s = state.clone();
+    engine.register_fn("player_hp", move || s.borrow().hp as INT);
+    let s = state.clone();
+    engine.register_fn("player_level", move || s.borrow().level as INT);
+    let s = state.clone();
+    engine.register_fn("player_gold", move || s.borrow().gold as INT);
+    let s = state.clone();
+    engine.register_fn("current_chunk", move || s.borrow().chunk.clone());
+    let s = state.clone();
+    engine.register_fn("world_time", move || s.borrow().world_time as FLOAT);
+
+    // Story flags
+    let s = state.clone();
+    engine.register_fn("has_flag", move |flag: &str| s.borrow().flags.contains(flag));
+    let s = state.clone();
+    engine.register_fn("set_flag", move |flag: &str| {
+        let mut state = s.borrow_mut();
+        state.flags.insert(flag.to_string());
+        state.commands.push(ScriptCommand::Action(TriggerAction::SetFlag(flag.to_string())));
+    });
+    let s = state.clone();
+    engine.register_fn("clear_flag", move |flag: &str| {
+        let mut state = s.borrow_mut();
+        state.flags.remove(flag);
+        state.commands.push(ScriptCommand::Action(TriggerAction::ClearFlag(flag.to_string())));
+    });
+
+    // Items and gold
+    let s = state.clone();
+    engine.register_fn("item_count", move |item: &str| {
+        s.borrow().inventory.iter().filter(|id| *id == item).count() as INT
+    });
+    let s = state.clone();
+    engine.register_fn("give_item", move |item: &str| {
+        let mut state = s.borrow_mut();
+        state.inventory.push(item.to_string());
+        state.commands.push(ScriptCommand::GiveItem(item.to_string()));
+    });
+    let s = state.clone();
+    engine.register_fn("take_item", move |item: &str| {
+        let mut state = s.borrow_mut();
+        match state.inventory.iter().position(|id| id == item) {
+            Some(index) => {
+                state.inventory.remove(index);
+                state.commands.push(ScriptCommand::TakeItem(item.to_string()));
+                true
+            },
+            None => false,
+        }
+    });
+    let s = state.clone();
+    engine.register_fn("give_gold", move |gold: INT| {
+        let mut state = s.borrow_mut();
+        state.gold += gold as i32;
+        state.commands.push(ScriptCommand::GiveGold(gold as i32));
+    });
+    let s = state.clone();
+    engine.register_fn("heal", move |hp: INT| {
+        s.borrow_mut().commands.push(ScriptCommand::Heal(hp as i32));
+    });
+
+    let names: HashMap<String, (String, i32)> = items.iter()
+        .map(|(id, item)| (id.clone(), (item.name.clone(), item.price)))
+        .collect();
+    let item_names = names.clone();
+    engine.register_fn("item_name", move |item: &str| {
+        item_names.get(item).map(|(name, _)| name.clone()).unwrap_or_else(|| item.to_string())
+    });
+    engine.register_fn("item_price", move |item: &str| {
+        names.get(item).map(|(_, price)| *price as INT).unwrap_or(0)
+    });
+
+    // World events, same as the trigger actions of the same name
+    let s = state.clone();
+    engine.register_fn("show_text", move |text: &str| {
+        s.borrow_mut().commands.push(ScriptCommand::Action(TriggerAction::ShowText(text.to_string())));
+    });
+    let s = state.clone();
+    engine.register_fn("warp", move |chunk: &str, x: INT, y: INT| {
+        s.borrow_mut().commands.push(ScriptCommand::Action(TriggerAction::Warp {
+            chunk: Some(chunk.to_string()),
+            x: x as f32,
+            y: y as f32,
+        }));
+    });
+    let s = state.clone();
+    engine.register_fn("spawn_monster", move |monster: &str, x: INT, y: INT| {
+        s.borrow_mut().commands.push(ScriptCommand::Action(TriggerAction::SpawnMonster {
+            monster: monster.to_string(),
+            x: x as f32,
+            y: y as f32,
+        }));
+    });
+}
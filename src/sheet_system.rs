@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use serde_json::Value;
+use crate::color_system::parse_color;
+use crate::sprite_system::{CharacterSprite, SpriteSheet, load_sheet, load_sprites};
+
+// Frames per row of an exported sheet
+const SHEET_COLUMNS: usize = 8;
+
+// `--export-sprites <sprites.json> <out_dir>` on the command line
+pub fn export_args() -> Option<(String, String)> {
+    command_args("--export-sprites")
+}
+
+// `--import-sprites <sheet.json> <sprites.json>` on the command line
+pub fn import_args() -> Option<(String, String)> {
+    command_args("--import-sprites")
+}
+
+fn command_args(flag: &str) -> Option<(String, String)> {
+    let args: Vec<String> = std::env::args().collect();
+    let i = args.iter().position(|arg| arg == flag)?;
+    Some((args.get(i + 1)?.clone(), args.get(i + 2)?.clone()))
+}
+
+// Writes every sprite of a sprite file as `<name>.png` plus `<name>.json`
+// metadata into `out_dir`, ready to open in an image editor. Returns how
+// many sprites were written.
+pub fn export_sprites(sprites_path: &str, out_dir: &str) -> Result<usize, String> {
+    let data = load_sprites(sprites_path)?;
+    if let Err(e) = std::fs::create_dir_all(out_dir) {
+        return Err(format!("Sheet directory error: {}: {}", out_dir, e));
+    }
+    let mut names: Vec<&String> = data.sprites.keys().collect();
+    names.sort();
+    for name in &names {
+        let sprite = &data.sprites[*name];
+        let (frame_width, frame_height) = sprite.size(&data.sprite_info);
+        let colors: HashMap<char, [u8; 4]> = data.color_palette.iter()
+            .chain(&sprite.palette)
+            .filter_map(|(key, color)| Some((key.chars().next()?, parse_color(color).ok()?.into())))
+            .collect();
+
+        // Plain frames first, then each direction's, both in name order
+        let mut frames: Vec<(String, &Vec<String>)> = sprite.frames.iter()
+            .map(|(frame_name, rows)| (frame_name.clone(), rows))
+            .collect();
+        frames.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut directional: Vec<(String, &Vec<String>)> = sprite.directions.iter()
+            .flat_map(|(direction, frames)| frames.iter().map(move |(frame_name, rows)| (format!("{}/{}", direction, frame_name), rows)))
+            .collect();
+        directional.sort_by(|a, b| a.0.cmp(&b.0));
+        frames.extend(directional);
+
+        let columns = frames.len().clamp(1, SHEET_COLUMNS);
+        let width = columns * frame_width;
+        let height = frames.len().div_ceil(columns).max(1) * frame_height;
+        let mut pixels = vec![0u8; width * height * 4];
+        for (i, (_, rows)) in frames.iter().enumerate() {
+            let origin_x = (i % columns) * frame_width;
+            let origin_y = (i / columns) * frame_height;
+            for (y, row) in rows.iter().enumerate().take(frame_height) {
+                for (x, ch) in row.chars().enumerate().take(frame_width) {
+                    if let Some(rgba) = colors.get(&ch) {
+                        let offset = ((origin_y + y) * width + origin_x + x) * 4;
+                        pixels[offset..offset + 4].copy_from_slice(rgba);
+                    }
+                }
+            }
+        }
+
+        let image = format!("{}.png", name);
+        write_png(&Path::new(out_dir).join(&image), width, height, &pixels)?;
+        let sheet = SpriteSheet {
+            name: sprite.name.clone(),
+            image,
+            frame_width,
+            frame_height,
+            anchor: sprite.anchor,
+            frames: frames.into_iter().map(|(frame_name, _)| frame_name).collect(),
+            animations: sprite.animations.iter().map(|(key, animation)| (key.clone(), animation.clone())).collect(),
+            palette: sprite.palette.iter().map(|(key, color)| (key.clone(), color.clone())).collect(),
+        };
+        let json = match serde_json::to_string_pretty(&sheet) {
+            Ok(json) => json,
+            Err(e) => return Err(format!("Sheet JSON writing error: {}", e)),
+        };
+        let sheet_path = Path::new(out_dir).join(format!("{}.json", name));
+        if let Err(e) = std::fs::write(&sheet_path, json) {
+            return Err(format!("Sheet file write error: {}: {}", sheet_path.display(), e));
+        }
+    }
+    Ok(names.len())
+}
+
+// Reads a sheet into a sprite file as ASCII frames, under the sheet file's
+// name, through `save_sprites`. Returns the sprite's name.
+pub fn import_sheet(sheet_path: &str, sprites_path: &str) -> Result<String, String> {
+    let data = load_sprites(sprites_path)?;
+    let mut sprite = load_sheet(sheet_path, &data)?;
+    let name = match Path::new(sheet_path).file_stem().and_then(|stem| stem.to_str()) {
+        Some(name) => name.to_string(),
+        None => return Err(format!("Sheet {} has no file name to name the sprite after", sheet_path)),
+    };
+
+    // Where a character sharing its color with another was read back as the
+    // other one, keep what the old frame had
+    if let Some(old) = data.sprites.get(&name) {
+        let colors: HashMap<char, [u8; 4]> = data.color_palette.iter()
+            .chain(&sprite.palette)
+            .filter_map(|(key, color)| Some((key.chars().next()?, parse_color(color).ok()?.into())))
+            .collect();
+        let same_color = |a: char, b: char| a == b || colors.get(&a).is_some_and(|color| colors.get(&b) == Some(color));
+        let new_frames = sprite.frames.iter_mut()
+            .map(|(frame_name, rows)| (old.frames.get(frame_name), rows))
+            .chain(sprite.directions.iter_mut().flat_map(|(direction, frames)| {
+                frames.iter_mut().map(|(frame_name, rows)| (old.directions.get(direction).and_then(|old| old.get(frame_name)), rows))
+            }));
+        for (old_rows, rows) in new_frames {
+            let Some(old_rows) = old_rows else { continue };
+            for (row, old_row) in rows.iter_mut().zip(old_rows) {
+                let mut old_chars = old_row.chars();
+                *row = row.chars()
+                    .map(|ch| match old_chars.next() {
+                        Some(old_ch) if same_color(ch, old_ch) => old_ch,
+                        _ => ch,
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    save_sprites(sprites_path, &[(&name, &sprite)])?;
+    Ok(name)
+}
+
+// Writes `sprites` into a sprite file under their names, replacing any
+// already there. The rest of the file is left as it was, key order
+// included, and a sprite that already existed keeps the order of its frames
+// and animations.
+pub fn save_sprites(sprites_path: &str, sprites: &[(&str, &CharacterSprite)]) -> Result<(), String> {
+    let contents = match std::fs::read_to_string(sprites_path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("File read error: {}", e)),
+    };
+    let mut file: Value = match serde_json::from_str(&contents) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("JSON parsing error: {}", e)),
+    };
+    let file_sprites = match file.get_mut("sprites").and_then(Value::as_object_mut) {
+        Some(file_sprites) => file_sprites,
+        None => return Err(format!("{} has no sprites", sprites_path)),
+    };
+    for (name, sprite) in sprites {
+        // Through a string so f32 durations stay as short as they were written
+        let sprite: Value = match serde_json::to_string(sprite).map(|json| serde_json::from_str(&json)) {
+            Ok(Ok(sprite)) => sprite,
+            Ok(Err(e)) | Err(e) => return Err(format!("Sprite JSON writing error: {}", e)),
+        };
+        // A new sprite is laid out like the first one in the file
+        let sprite = match file_sprites.get(*name).or_else(|| file_sprites.values().next()) {
+            Some(old) => ordered_like(sprite, old),
+            None => ordered_like(sprite, &Value::Null),
+        };
+        file_sprites.insert(name.to_string(), sprite);
+    }
+
+    let json = match serde_json::to_string_pretty(&file) {
+        Ok(json) => json,
+        Err(e) => return Err(format!("Sprite JSON writing error: {}", e)),
+    };
+    match std::fs::write(sprites_path, json) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Sprite file write error: {}: {}", sprites_path, e)),
+    }
+}
+
+// `value` with its object keys in the order `old` has them, and keys `old`
+// doesn't have after those in name order, all the way down. Numbers equal to
+// the old ones keep how they were written, so `16` doesn't turn into `16.0`.
+fn ordered_like(value: Value, old: &Value) -> Value {
+    match value {
+        Value::Object(mut map) => {
+            let mut ordered = serde_json::Map::new();
+            if let Value::Object(old_map) = old {
+                for key in old_map.keys() {
+                    if let Some(child) = map.remove(key) {
+                        ordered.insert(key.clone(), ordered_like(child, &old_map[key]));
+                    }
+                }
+            }
+            let mut rest: Vec<(String, Value)> = map.into_iter().collect();
+            rest.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, child) in rest {
+                ordered.insert(key, ordered_like(child, &Value::Null));
+            }
+            Value::Object(ordered)
+        },
+        Value::Array(items) => {
+            let old_items = old.as_array();
+            Value::Array(items.into_iter().enumerate()
+                .map(|(i, item)| ordered_like(item, old_items.and_then(|old| old.get(i)).unwrap_or(&Value::Null)))
+                .collect())
+        },
+        Value::Number(number) if old.as_f64().is_some() && number.as_f64() == old.as_f64() => old.clone(),
+        other => other,
+    }
+}
+
+fn write_png(path: &Path, width: usize, height: usize, pixels: &[u8]) -> Result<(), String> {
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("Sheet image write error: {}: {}", path.display(), e)),
+    };
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let result = encoder.write_header().and_then(|mut writer| writer.write_image_data(pixels));
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("PNG encoding error: {}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SPRITE_FILES: [&str; 2] = ["data/sprites/character_sprites.json", "data/sprites/desert_sprites.json"];
+
+    // Every sprite of the game's sprite files, out to PNG and back in
+    #[test]
+    fn sheets_round_trip_to_the_same_frames_and_file() {
+        for sprites_path in SPRITE_FILES {
+            let dir = std::env::temp_dir().join(format!("sheet_test_{}_{}", std::process::id(), Path::new(sprites_path).file_stem().unwrap().to_string_lossy()));
+            let sheets_dir = dir.join("sheets");
+            let copy = dir.join("sprites.json");
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::copy(sprites_path, &copy).unwrap();
+            let copy = copy.to_string_lossy().into_owned();
+
+            let count = export_sprites(sprites_path, &sheets_dir.to_string_lossy()).unwrap();
+            let mut sheets: Vec<String> = std::fs::read_dir(&sheets_dir).unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            sheets.sort();
+            assert_eq!(sheets.len(), count);
+            for sheet in &sheets {
+                import_sheet(sheet, &copy).unwrap();
+            }
+
+            let original = load_sprites(sprites_path).unwrap();
+            let imported = load_sprites(&copy).unwrap();
+            for (name, sprite) in &original.sprites {
+                let back = &imported.sprites[name];
+                assert_eq!(sprite.frames, back.frames, "{} frames", name);
+                assert_eq!(sprite.directions, back.directions, "{} directions", name);
+            }
+            let unchanged = std::fs::read_to_string(sprites_path).unwrap() == std::fs::read_to_string(&copy).unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+            assert!(unchanged, "{} changed after a round trip", sprites_path);
+        }
+    }
+
+    #[test]
+    fn ordered_like_keeps_key_order_and_numbers() {
+        let old = json!({"name": "Slime", "width": 16, "anchor": [8, 12], "frames": {"walk1": [], "idle": []}});
+        let new = json!({"anchor": [8.0, 12.5], "frames": {"idle": [], "hurt1": [], "walk1": []}, "name": "Slime", "width": 16.0, "animations": {}});
+        let ordered = ordered_like(new, &old);
+        // Old keys in their old order, then new ones by name; numbers equal
+        // to the old ones are written as they were
+        assert_eq!(
+            serde_json::to_string(&ordered).unwrap(),
+            r#"{"name":"Slime","width":16,"anchor":[8,12.5],"frames":{"walk1":[],"idle":[],"hurt1":[]},"animations":{}}"#,
+        );
+    }
+}
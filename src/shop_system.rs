@@ -0,0 +1,207 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::biome_system::ItemConfig;
+use crate::sprite_system::{Facing, SpriteRenderer};
+
+// A merchant as placed in map YAML; x/y are tile coordinates like monster spawns
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MerchantData {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub stock: Vec<String>,
+    #[serde(default = "default_sell_ratio")]
+    pub sell_ratio: f32,
+    #[serde(default = "default_merchant_sprite")]
+    pub sprite: String,
+}
+
+fn default_sell_ratio() -> f32 {
+    0.5
+}
+
+fn default_merchant_sprite() -> String {
+    "merchant".to_string()
+}
+
+impl MerchantData {
+    pub fn sell_price(&self, item: &ItemConfig) -> i32 {
+        (item.price as f32 * self.sell_ratio).floor() as i32
+    }
+}
+
+pub const INTERACT_RANGE: f32 = 48.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ShopTab {
+    Buy,
+    Sell,
+}
+
+pub struct ShopPanel {
+    merchant: Option<MerchantData>,
+    tab: ShopTab,
+    selected: usize,
+    message: String,
+}
+
+impl ShopPanel {
+    pub fn new() -> Self {
+        ShopPanel {
+            merchant: None,
+            tab: ShopTab::Buy,
+            selected: 0,
+            message: String::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.merchant.is_some()
+    }
+
+    pub fn open(&mut self, merchant: MerchantData) {
+        self.message = format!("Welcome to {}'s shop!", merchant.name);
+        self.merchant = Some(merchant);
+        self.tab = ShopTab::Buy;
+        self.selected = 0;
+    }
+
+    pub fn update(&mut self, gold: &mut i32, inventory: &mut Vec<String>, items: &HashMap<String, ItemConfig>) {
+        let merchant = match &self.merchant {
+            Some(merchant) => merchant,
+            None => return,
+        };
+
+        if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::E) {
+            self.merchant = None;
+            return;
+        }
+
+        if is_key_pressed(KeyCode::Tab) || is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::Right) {
+            self.tab = if self.tab == ShopTab::Buy { ShopTab::Sell } else { ShopTab::Buy };
+            self.selected = 0;
+        }
+
+        let row_count = match self.tab {
+            ShopTab::Buy => merchant.stock.len(),
+            ShopTab::Sell => inventory.len(),
+        };
+        if is_key_pressed(KeyCode::Up) && self.selected > 0 {
+            self.selected -= 1;
+        }
+        if is_key_pressed(KeyCode::Down) && self.selected + 1 < row_count {
+            self.selected += 1;
+        }
+
+        if !is_key_pressed(KeyCode::Enter) || row_count == 0 {
+            return;
+        }
+
+        match self.tab {
+            ShopTab::Buy => {
+                let item_id = &merchant.stock[self.selected];
+                if let Some(item) = items.get(item_id) {
+                    if *gold >= item.price {
+                        *gold -= item.price;
+                        inventory.push(item_id.clone());
+                        self.message = format!("Bought {} for {} gold", item.name, item.price);
+                    } else {
+                        self.message = format!("Not enough gold for {}", item.name);
+                    }
+                }
+            },
+            ShopTab::Sell => {
+                // Items missing from the item list are kept, not sold for nothing
+                match items.get(&inventory[self.selected]) {
+                    Some(item) => {
+                        let price = merchant.sell_price(item);
+                        *gold += price;
+                        self.message = format!("Sold {} for {} gold", item.name, price);
+                        inventory.remove(self.selected);
+                        self.selected = self.selected.min(inventory.len().saturating_sub(1));
+                    },
+                    None => self.message = format!("{} can't be sold here", inventory[self.selected]),
+                }
+            },
+        }
+    }
+
+    pub fn draw(&self, gold: i32, inventory: &[String], items: &HashMap<String, ItemConfig>) {
+        let merchant = match &self.merchant {
+            Some(merchant) => merchant,
+            None => return,
+        };
+
+        let rows: &[String] = match self.tab {
+            ShopTab::Buy => &merchant.stock,
+            ShopTab::Sell => inventory,
+        };
+
+        let panel_width = 380.0;
+        let panel_height = 150.0 + rows.len().max(1) as f32 * 22.0;
+        let panel_x = screen_width() / 2.0 - panel_width / 2.0;
+        let panel_y = screen_height() / 2.0 - panel_height / 2.0;
+
+        draw_rectangle(panel_x, panel_y, panel_width, panel_height, Color::new(0.0, 0.0, 0.0, 0.85));
+        draw_rectangle_lines(panel_x, panel_y, panel_width, panel_height, 2.0, GOLD);
+        draw_text(&format!("=== {} ===", merchant.name), panel_x + 10.0, panel_y + 25.0, 20.0, YELLOW);
+
+        let buy_color = if self.tab == ShopTab::Buy { YELLOW } else { GRAY };
+        let sell_color = if self.tab == ShopTab::Sell { YELLOW } else { GRAY };
+        draw_text("[Buy]", panel_x + 20.0, panel_y + 50.0, 18.0, buy_color);
+        draw_text("[Sell]", panel_x + 80.0, panel_y + 50.0, 18.0, sell_color);
+        draw_text(&format!("Gold: {}", gold), panel_x + panel_width - 110.0, panel_y + 50.0, 18.0, GOLD);
+
+        let mut y = panel_y + 80.0;
+        if rows.is_empty() {
+            draw_text("(nothing)", panel_x + 20.0, y, 18.0, GRAY);
+            y += 22.0;
+        }
+        for (i, item_id) in rows.iter().enumerate() {
+            let color = if i == self.selected { YELLOW } else { WHITE };
+            match items.get(item_id) {
+                Some(item) => {
+                    let price = match self.tab {
+                        ShopTab::Buy => item.price,
+                        ShopTab::Sell => merchant.sell_price(item),
+                    };
+                    draw_text(&item.name, panel_x + 20.0, y, 18.0, color);
+                    draw_text(&format!("{} G", price), panel_x + panel_width - 90.0, y, 18.0, color);
+                },
+                None => {
+                    draw_text(item_id, panel_x + 20.0, y, 18.0, GRAY);
+                },
+            }
+            y += 22.0;
+        }
+
+        draw_text(&self.message, panel_x + 10.0, y + 10.0, 16.0, LIGHTGRAY);
+        draw_text("TAB: Buy/Sell, UP/DOWN: Select, ENTER: Confirm, E: Leave", panel_x + 10.0, y + 35.0, 14.0, GRAY);
+    }
+}
+
+pub fn draw_merchant(
+    sprite_renderer: &SpriteRenderer,
+    merchant: &MerchantData,
+    screen_pos: Vec2,
+    player_nearby: bool,
+) {
+    if sprite_renderer.has_frame(&merchant.sprite, "idle") {
+        sprite_renderer.draw_sprite_outlined(&merchant.sprite, "idle", screen_pos, 2.0, Facing::Down.into(), BLACK);
+    } else {
+        draw_circle(screen_pos.x, screen_pos.y, 12.0, GOLD);
+    }
+
+    // Labels go just above and below the sprite, whatever its size
+    let bounds = sprite_renderer.bounds(&merchant.sprite, screen_pos, 2.0)
+        .unwrap_or(Rect::new(screen_pos.x - 16.0, screen_pos.y - 16.0, 32.0, 32.0));
+    let name_width = measure_text(&merchant.name, None, 16, 1.0).width;
+    draw_text(&merchant.name, screen_pos.x - name_width / 2.0, bounds.y - 6.0, 16.0, GOLD);
+
+    if player_nearby {
+        let prompt = "[E] Trade";
+        let prompt_width = measure_text(prompt, None, 16, 1.0).width;
+        draw_text(prompt, screen_pos.x - prompt_width / 2.0, bounds.bottom() + 18.0, 16.0, WHITE);
+    }
+}
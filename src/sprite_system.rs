@@ -1,12 +1,19 @@
 use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::path::Path;
+use crate::color_system::parse_color;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SpriteData {
     pub sprite_info: SpriteInfo,
     pub color_palette: HashMap<String, String>,
     pub sprites: HashMap<String, CharacterSprite>,
+    // Sprites kept as PNG sheets: sprite name to the sheet's metadata file,
+    // relative to this file. They're read into `sprites` on load.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sheets: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -20,65 +27,371 @@ pub struct SpriteInfo {
 pub struct CharacterSprite {
     pub name: String,
     pub frames: HashMap<String, Vec<String>>,
+    // Frames drawn instead of `frames` when facing a direction, keyed by
+    // `down`, `up` and `side` (drawn facing right) or any of the eight
+    // direction names. Missing ones are mirrored from the opposite side or
+    // fall back to `frames`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub directions: HashMap<String, HashMap<String, Vec<String>>>,
+    // Named sequences of frames (idle, walk, attack, hurt, death)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub animations: HashMap<String, Animation>,
+    // Colors used instead of the shared palette's for this sprite only
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub palette: HashMap<String, String>,
+    // Size in pixels when it isn't the file's `sprite_info` size, for bosses
+    // and other big sprites
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<usize>,
+    // The pixel that sits on the entity's position, from the top left; the
+    // middle of the sprite when not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<[f32; 2]>,
+}
+
+impl CharacterSprite {
+    pub fn size(&self, info: &SpriteInfo) -> (usize, usize) {
+        (self.width.unwrap_or(info.width), self.height.unwrap_or(info.height))
+    }
+    
+    pub fn anchor_point(&self, info: &SpriteInfo) -> Vec2 {
+        let (width, height) = self.size(info);
+        match self.anchor {
+            Some([x, y]) => vec2(x, y),
+            None => vec2(width as f32, height as f32) / 2.0,
+        }
+    }
+}
+
+impl SpriteData {
+    // Adds a copy of `base` under `name` with some palette colors swapped,
+    // for variants that share a drawing but not its colors. False when
+    // there's no such base sprite.
+    pub fn add_recolor(&mut self, name: &str, base: &str, palette: &HashMap<String, String>) -> bool {
+        let mut sprite = match self.sprites.get(base) {
+            Some(sprite) => sprite.clone(),
+            None => return false,
+        };
+        sprite.palette.extend(palette.iter().map(|(key, color)| (key.clone(), color.clone())));
+        self.sprites.insert(name.to_string(), sprite);
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Animation {
+    pub frames: Vec<AnimationFrame>,
+    #[serde(default, skip_serializing_if = "PlayMode::is_loop")]
+    pub mode: PlayMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnimationFrame {
+    pub frame: String,
+    // Seconds the frame stays on screen
+    pub duration: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayMode {
+    // Starts over after the last frame
+    #[default]
+    Loop,
+    // Plays through once, then hands back to the entity's looping state
+    Once,
+}
+
+impl PlayMode {
+    fn is_loop(&self) -> bool {
+        *self == PlayMode::Loop
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Facing {
+    #[default]
+    Down,
+    DownRight,
+    Right,
+    UpRight,
+    Up,
+    UpLeft,
+    Left,
+    DownLeft,
+}
+
+// Direction names sprite files may use, besides `side`
+const DIRECTION_NAMES: [&str; 8] = ["down", "down_right", "right", "up_right", "up", "up_left", "left", "down_left"];
+
+impl Facing {
+    const ALL: [Facing; 8] = [
+        Facing::Down, Facing::DownRight, Facing::Right, Facing::UpRight,
+        Facing::Up, Facing::UpLeft, Facing::Left, Facing::DownLeft,
+    ];
+    
+    // The nearest of the eight directions; None for a zero vector
+    pub fn from_vector(direction: Vec2) -> Option<Facing> {
+        if direction.length_squared() == 0.0 {
+            return None;
+        }
+        // Screen y points down, so Down is a quarter turn from Right
+        let angle = direction.y.atan2(direction.x);
+        let sector = (angle / std::f32::consts::FRAC_PI_4).round() as i32;
+        Some(Facing::ALL[(2 - sector).rem_euclid(8) as usize])
+    }
+    
+    pub fn name(self) -> &'static str {
+        DIRECTION_NAMES[self as usize]
+    }
+    
+    pub fn is_left(self) -> bool {
+        matches!(self, Facing::UpLeft | Facing::Left | Facing::DownLeft)
+    }
+    
+    fn mirrored(self) -> Facing {
+        Facing::ALL[(8 - self as usize) % 8]
+    }
+    
+    // Which frame sets can stand in for this direction, best first, and
+    // whether each needs flipping
+    fn candidates(self) -> Vec<(&'static str, bool)> {
+        let mut candidates = vec![(self.name(), false)];
+        if self != self.mirrored() {
+            candidates.push((self.mirrored().name(), true));
+        }
+        let sideways = match self {
+            Facing::DownRight | Facing::UpRight => Some(Facing::Right),
+            Facing::DownLeft | Facing::UpLeft => Some(Facing::Left),
+            _ => None,
+        };
+        if let Some(side) = sideways {
+            candidates.push((side.name(), false));
+            candidates.push((side.mirrored().name(), true));
+        }
+        if self.is_left() {
+            candidates.push(("side", true));
+        } else if self != Facing::Down && self != Facing::Up {
+            candidates.push(("side", false));
+        }
+        candidates
+    }
+}
+
+// Which way an entity faces, plus which side it last faced. Plain frames
+// flip by the side, so walking straight up or down keeps a character that
+// was looking left looking left.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Heading {
+    pub facing: Facing,
+    left: bool,
+}
+
+impl Heading {
+    pub fn turn(&mut self, facing: Facing) {
+        self.facing = facing;
+        match facing {
+            Facing::Down | Facing::Up => {}
+            _ => self.left = facing.is_left(),
+        }
+    }
+}
+
+impl From<Facing> for Heading {
+    fn from(facing: Facing) -> Heading {
+        Heading { facing, left: facing.is_left() }
+    }
+}
+
+// Shortest time a frame is shown, so a zero duration can't spin forever
+const MIN_FRAME_TIME: f32 = 0.01;
+
+// Which animation an entity is showing and how far into it it is. Entities
+// set a looping state every frame (idle, walk) and trigger one-shots on
+// events (attack, hurt, death), which play to the end before the state
+// takes over again.
+#[derive(Debug, Clone)]
+pub struct Animator {
+    state: String,
+    current: String,
+    frame: usize,
+    timer: f32,
+    // A one-shot animation that hasn't reached its end yet
+    playing_once: bool,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Animator {
+            state: "idle".to_string(),
+            current: "idle".to_string(),
+            frame: 0,
+            timer: 0.0,
+            playing_once: false,
+        }
+    }
+    
+    pub fn set_state(&mut self, name: &str) {
+        if self.state != name {
+            self.state = name.to_string();
+        }
+        if !self.playing_once && self.current != self.state {
+            self.start(&self.state.clone());
+        }
+    }
+    
+    pub fn play_once(&mut self, name: &str) {
+        self.start(name);
+        self.playing_once = true;
+    }
+    
+    // True while `name` is the animation on screen and hasn't ended
+    pub fn is_playing(&self, name: &str) -> bool {
+        self.current == name && self.playing_once
+    }
+    
+    fn start(&mut self, name: &str) {
+        self.current = name.to_string();
+        self.frame = 0;
+        self.timer = 0.0;
+    }
+    
+    pub fn update(&mut self, renderer: &SpriteRenderer, sprite_name: &str, delta: f32) {
+        let animation = match renderer.animation(sprite_name, &self.current) {
+            Some(animation) if !animation.frames.is_empty() => animation,
+            // Nothing to time; a one-shot without frames is over at once
+            _ => {
+                if self.playing_once {
+                    self.playing_once = false;
+                    self.set_state(&self.state.clone());
+                }
+                return;
+            },
+        };
+        
+        // The same sprite can differ between biomes' sprite files
+        self.frame = self.frame.min(animation.frames.len() - 1);
+        self.timer += delta;
+        loop {
+            let duration = animation.frames[self.frame].duration.max(MIN_FRAME_TIME);
+            if self.timer < duration {
+                break;
+            }
+            self.timer -= duration;
+            if self.frame + 1 < animation.frames.len() {
+                self.frame += 1;
+            } else if animation.mode == PlayMode::Loop {
+                self.frame = 0;
+            } else {
+                // Hold the last frame until the state takes over
+                self.timer = 0.0;
+                if self.playing_once {
+                    self.playing_once = false;
+                    self.set_state(&self.state.clone());
+                }
+                break;
+            }
+        }
+    }
+    
+    // The sprite frame to draw. Sprites without the animation fall back to a
+    // frame of the same name, then to their idle frame.
+    pub fn frame<'a>(&'a self, renderer: &'a SpriteRenderer, sprite_name: &str) -> &'a str {
+        match renderer.animation(sprite_name, &self.current) {
+            Some(animation) if !animation.frames.is_empty() => {
+                &animation.frames[self.frame.min(animation.frames.len() - 1)].frame
+            },
+            _ if renderer.has_frame(sprite_name, &self.current) => &self.current,
+            _ => "idle",
+        }
+    }
+}
+
+// Each sprite pixel is baked as this many atlas pixels per side, so the
+// one-atlas-pixel outline is as thin as the old one-screen-pixel outline at
+// the usual scale of 2
+const ATLAS_SUBPIXELS: usize = 2;
+// Widest the atlas grows before starting a new row of cells
+const ATLAS_MAX_WIDTH: usize = 2048;
+
+// Where a frame was baked: the sprite itself and, next to it, a white
+// silhouette grown by one atlas pixel that is tinted to draw the outline.
+// `anchor` is the sprite's anchor in atlas pixels from the cell's corner.
+#[derive(Clone, Copy)]
+struct BakedFrame {
+    sprite: Rect,
+    outline: Rect,
+    anchor: Vec2,
 }
 
 pub struct SpriteRenderer {
     sprite_data: SpriteData,
-    color_cache: HashMap<String, Color>,
+    atlas: Texture2D,
+    frames: HashMap<String, HashMap<String, BakedFrame>>,
 }
 
 impl SpriteRenderer {
     pub fn new(sprite_data: SpriteData) -> Self {
         let mut color_cache = HashMap::new();
         
-        // Pre-parse colors
+        // Pre-parse colors, all checked when the file loaded
         for (key, color_str) in &sprite_data.color_palette {
-            let color = parse_color(color_str);
+            let color = parse_color(color_str).unwrap_or(WHITE);
             color_cache.insert(key.clone(), color);
         }
         
+        let (image, frames) = bake_atlas(&sprite_data, &color_cache);
+        let atlas = Texture2D::from_image(&image);
+        atlas.set_filter(FilterMode::Nearest);
+        
         SpriteRenderer {
             sprite_data,
-            color_cache,
+            atlas,
+            frames,
         }
     }
     
+    pub fn animation(&self, sprite_name: &str, animation_name: &str) -> Option<&Animation> {
+        self.sprite_data.sprites.get(sprite_name)?.animations.get(animation_name)
+    }
+    
+    // Where the sprite covers when drawn at `position` (facing right), for
+    // placing health bars and labels and sizing hitboxes
+    pub fn bounds(&self, sprite_name: &str, position: Vec2, scale: f32) -> Option<Rect> {
+        let info = &self.sprite_data.sprite_info;
+        let sprite = self.sprite_data.sprites.get(sprite_name)?;
+        let (width, height) = sprite.size(info);
+        let corner = position - sprite.anchor_point(info) * scale;
+        Some(Rect::new(corner.x, corner.y, width as f32 * scale, height as f32 * scale))
+    }
+    
+    // A circle a little inside the sprite for collisions, radius 15 for the
+    // usual 16x16 sprite drawn at 2x
+    pub fn hitbox(&self, sprite_name: &str, position: Vec2, scale: f32) -> (Vec2, f32) {
+        match self.bounds(sprite_name, position, scale) {
+            Some(bounds) => (bounds.center(), bounds.w.min(bounds.h) / 2.0 - 1.0),
+            None => (position, 15.0),
+        }
+    }
+    
+    pub fn has_frame(&self, sprite_name: &str, frame_name: &str) -> bool {
+        self.sprite_data.sprites.get(sprite_name)
+            .map(|sprite| sprite.frames.contains_key(frame_name))
+            .unwrap_or(false)
+    }
+    
     pub fn draw_sprite(
         &self,
         sprite_name: &str,
         frame_name: &str,
         position: Vec2,
         scale: f32,
-        flip_x: bool,
+        heading: Heading,
     ) {
-        if let Some(sprite) = self.sprite_data.sprites.get(sprite_name) {
-            if let Some(frame) = sprite.frames.get(frame_name) {
-                let pixel_size = scale;
-                let sprite_width = self.sprite_data.sprite_info.width as f32 * pixel_size;
-                let sprite_height = self.sprite_data.sprite_info.height as f32 * pixel_size;
-                
-                // Center the sprite
-                let start_x = position.x - sprite_width / 2.0;
-                let start_y = position.y - sprite_height / 2.0;
-                
-                for (y, row) in frame.iter().enumerate() {
-                    for (x, ch) in row.chars().enumerate() {
-                        let key = ch.to_string();
-                        if let Some(color) = self.color_cache.get(&key) {
-                            if key != "." {  // Skip transparent pixels
-                                let pixel_x = if flip_x {
-                                    start_x + (self.sprite_data.sprite_info.width - 1 - x) as f32 * pixel_size
-                                } else {
-                                    start_x + x as f32 * pixel_size
-                                };
-                                let pixel_y = start_y + y as f32 * pixel_size;
-                                
-                                draw_rectangle(pixel_x, pixel_y, pixel_size, pixel_size, *color);
-                            }
-                        }
-                    }
-                }
-            }
+        if let Some((baked, flip_x)) = self.baked_frame(sprite_name, frame_name, heading) {
+            self.draw_cell(baked.sprite, baked.anchor, position, scale, flip_x, WHITE);
         }
     }
     
@@ -88,104 +401,343 @@ impl SpriteRenderer {
         frame_name: &str,
         position: Vec2,
         scale: f32,
-        flip_x: bool,
+        heading: Heading,
         outline_color: Color,
     ) {
-        // Draw outline
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                if dx != 0 || dy != 0 {
-                    let offset_pos = position + vec2(dx as f32, dy as f32);
-                    self.draw_sprite_silhouette(sprite_name, frame_name, offset_pos, scale, flip_x, outline_color);
-                }
-            }
+        if let Some((baked, flip_x)) = self.baked_frame(sprite_name, frame_name, heading) {
+            self.draw_cell(baked.outline, baked.anchor, position, scale, flip_x, outline_color);
+            self.draw_cell(baked.sprite, baked.anchor, position, scale, flip_x, WHITE);
         }
-        
-        // Draw sprite on top
-        self.draw_sprite(sprite_name, frame_name, position, scale, flip_x);
     }
     
-    fn draw_sprite_silhouette(
-        &self,
-        sprite_name: &str,
-        frame_name: &str,
-        position: Vec2,
-        scale: f32,
-        flip_x: bool,
-        color: Color,
-    ) {
-        if let Some(sprite) = self.sprite_data.sprites.get(sprite_name) {
-            if let Some(frame) = sprite.frames.get(frame_name) {
-                let pixel_size = scale;
-                let sprite_width = self.sprite_data.sprite_info.width as f32 * pixel_size;
-                let sprite_height = self.sprite_data.sprite_info.height as f32 * pixel_size;
-                
-                let start_x = position.x - sprite_width / 2.0;
-                let start_y = position.y - sprite_height / 2.0;
-                
-                for (y, row) in frame.iter().enumerate() {
-                    for (x, ch) in row.chars().enumerate() {
-                        if ch != '.' {  // Any non-transparent pixel
-                            let pixel_x = if flip_x {
-                                start_x + (self.sprite_data.sprite_info.width - 1 - x) as f32 * pixel_size
-                            } else {
-                                start_x + x as f32 * pixel_size
-                            };
-                            let pixel_y = start_y + y as f32 * pixel_size;
-                            
-                            draw_rectangle(pixel_x, pixel_y, pixel_size, pixel_size, color);
-                        }
-                    }
-                }
+    // The frame drawn for a heading, and whether to flip it. Without a
+    // directional frame the plain one is used, flipped when the last side
+    // faced was left.
+    fn baked_frame(&self, sprite_name: &str, frame_name: &str, heading: Heading) -> Option<(BakedFrame, bool)> {
+        let frames = self.frames.get(sprite_name)?;
+        for (direction, flip_x) in heading.facing.candidates() {
+            if let Some(baked) = frames.get(&directional_key(direction, frame_name)) {
+                return Some((*baked, flip_x));
             }
         }
+        frames.get(frame_name).map(|baked| (*baked, heading.left))
+    }
+    
+    // Draws one atlas cell with its anchor on `position`; flipping mirrors
+    // the anchor too. Cells carry a one atlas pixel border for the outline,
+    // which is why they're drawn that much bigger than the sprite.
+    fn draw_cell(&self, source: Rect, anchor: Vec2, position: Vec2, scale: f32, flip_x: bool, color: Color) {
+        let pixel = scale / ATLAS_SUBPIXELS as f32;
+        let size = vec2(source.w, source.h) * pixel;
+        let anchor_x = if flip_x { source.w - anchor.x } else { anchor.x };
+        draw_texture_ex(&self.atlas, position.x - anchor_x * pixel, position.y - anchor.y * pixel, color, DrawTextureParams {
+            dest_size: Some(size),
+            source: Some(source),
+            flip_x,
+            ..Default::default()
+        });
     }
 }
 
-fn parse_color(color_str: &str) -> Color {
-    if color_str == "transparent" {
-        return Color::new(0.0, 0.0, 0.0, 0.0);
+// Rasterizes every frame once into a single texture so each sprite is drawn
+// as one quad (two with its outline) instead of one rectangle per pixel
+fn bake_atlas(
+    sprite_data: &SpriteData,
+    color_cache: &HashMap<String, Color>,
+) -> (Image, HashMap<String, HashMap<String, BakedFrame>>) {
+    let info = &sprite_data.sprite_info;
+    
+    // Sorted so the atlas comes out the same every run
+    let mut names: Vec<(&String, String, &Vec<String>)> = Vec::new();
+    let mut sprite_colors: HashMap<&String, HashMap<String, Color>> = HashMap::new();
+    for (sprite_name, sprite) in &sprite_data.sprites {
+        for (frame_name, frame) in &sprite.frames {
+            names.push((sprite_name, frame_name.clone(), frame));
+        }
+        for (direction, frames) in &sprite.directions {
+            for (frame_name, frame) in frames {
+                names.push((sprite_name, directional_key(direction, frame_name), frame));
+            }
+        }
+        if !sprite.palette.is_empty() {
+            let mut colors = color_cache.clone();
+            colors.extend(sprite.palette.iter().map(|(key, color)| (key.clone(), parse_color(color).unwrap_or(WHITE))));
+            sprite_colors.insert(sprite_name, colors);
+        }
+    }
+    names.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+    
+    // Each frame takes two cells side by side, the sprite and its outline,
+    // packed in rows as tall as the tallest sprite in them
+    let cell_size = |sprite_name: &String| {
+        let (width, height) = sprite_data.sprites[sprite_name].size(info);
+        (width * ATLAS_SUBPIXELS + 2, height * ATLAS_SUBPIXELS + 2)
+    };
+    let mut origins = Vec::with_capacity(names.len());
+    let (mut x, mut y, mut row_h, mut atlas_w) = (0, 0, 0, 1);
+    for (sprite_name, _, _) in &names {
+        let (cell_w, cell_h) = cell_size(sprite_name);
+        if x > 0 && x + cell_w * 2 > ATLAS_MAX_WIDTH {
+            x = 0;
+            y += row_h;
+            row_h = 0;
+        }
+        origins.push((x, y));
+        x += cell_w * 2;
+        row_h = row_h.max(cell_h);
+        atlas_w = atlas_w.max(x);
     }
+    let atlas_h = (y + row_h).max(1);
+    let mut image = Image::gen_image_color(atlas_w as u16, atlas_h as u16, Color::new(0.0, 0.0, 0.0, 0.0));
     
-    if color_str.starts_with('#') {
-        // Parse hex color
-        let hex = &color_str[1..];
-        if hex.len() == 6 {
-            if let Ok(r) = u8::from_str_radix(&hex[0..2], 16) {
-                if let Ok(g) = u8::from_str_radix(&hex[2..4], 16) {
-                    if let Ok(b) = u8::from_str_radix(&hex[4..6], 16) {
-                        return Color::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0);
+    let mut frames: HashMap<String, HashMap<String, BakedFrame>> = HashMap::new();
+    for ((sprite_name, frame_name, frame), (origin_x, origin_y)) in names.into_iter().zip(origins) {
+        let sprite = &sprite_data.sprites[sprite_name];
+        let (width, height) = sprite.size(info);
+        let (cell_w, cell_h) = cell_size(sprite_name);
+        let colors = sprite_colors.get(sprite_name).unwrap_or(color_cache);
+        
+        let mut solid = vec![false; cell_w * cell_h];
+        for (y, row) in frame.iter().enumerate().take(height) {
+            for (x, ch) in row.chars().enumerate().take(width) {
+                if ch == '.' {
+                    continue;
+                }
+                let color = match colors.get(&ch.to_string()) {
+                    Some(color) => *color,
+                    None => continue,
+                };
+                for sy in 0..ATLAS_SUBPIXELS {
+                    for sx in 0..ATLAS_SUBPIXELS {
+                        let cx = 1 + x * ATLAS_SUBPIXELS + sx;
+                        let cy = 1 + y * ATLAS_SUBPIXELS + sy;
+                        image.set_pixel((origin_x + cx) as u32, (origin_y + cy) as u32, color);
+                        solid[cy * cell_w + cx] = true;
                     }
                 }
             }
         }
+        
+        // The outline covers every atlas pixel touching the silhouette, the
+        // same shape the old eight offset copies drew
+        for cy in 0..cell_h {
+            for cx in 0..cell_w {
+                let touches = (-1..=1).any(|dy: i32| (-1..=1).any(|dx: i32| {
+                    let nx = cx as i32 + dx;
+                    let ny = cy as i32 + dy;
+                    nx >= 0 && ny >= 0 && (nx as usize) < cell_w && (ny as usize) < cell_h &&
+                        solid[ny as usize * cell_w + nx as usize]
+                }));
+                if touches {
+                    image.set_pixel((origin_x + cell_w + cx) as u32, (origin_y + cy) as u32, WHITE);
+                }
+            }
+        }
+        
+        let cell = |x: usize| Rect::new(x as f32, origin_y as f32, cell_w as f32, cell_h as f32);
+        frames.entry(sprite_name.clone()).or_default().insert(frame_name, BakedFrame {
+            sprite: cell(origin_x),
+            outline: cell(origin_x + cell_w),
+            anchor: sprite.anchor_point(info) * ATLAS_SUBPIXELS as f32 + vec2(1.0, 1.0),
+        });
+    }
+    
+    (image, frames)
+}
+
+// Where a direction's frame is kept in the baked atlas
+fn directional_key(direction: &str, frame_name: &str) -> String {
+    format!("{}/{}", direction, frame_name)
+}
+
+// A sprite drawn in an image editor. Frames sit left to right, then top to
+// bottom, in the order `frames` lists them; directional frames are named
+// like `up/idle`. Maps are sorted so exported sheets come out the same
+// every run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpriteSheet {
+    pub name: String,
+    // Relative to the metadata file
+    pub image: String,
+    pub frame_width: usize,
+    pub frame_height: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<[f32; 2]>,
+    pub frames: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub animations: BTreeMap<String, Animation>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub palette: BTreeMap<String, String>,
+}
+
+// Reads a sprite sheet back into rows of palette characters. Every pixel
+// becomes the palette color nearest to it, and mostly transparent pixels
+// become `.`; characters sharing a color come back as the first of them.
+pub fn load_sheet(path: &str, sprite_data: &SpriteData) -> Result<CharacterSprite, String> {
+    let sheet = match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            match serde_json::from_str::<SpriteSheet>(&contents) {
+                Ok(sheet) => sheet,
+                Err(e) => return Err(format!("Sheet JSON parsing error: {}: {}", path, e)),
+            }
+        },
+        Err(e) => return Err(format!("Sheet file read error: {}: {}", path, e)),
+    };
+    let info = &sprite_data.sprite_info;
+    if sheet.frame_width == 0 || sheet.frame_height == 0 {
+        return Err(format!("Sheet {} has empty {}x{} frames", path, sheet.frame_width, sheet.frame_height));
+    }
+    
+    let image_path = Path::new(path).parent().unwrap_or(Path::new(".")).join(&sheet.image);
+    let (width, height, pixels) = read_png(&image_path)?;
+    let columns = width / sheet.frame_width;
+    let capacity = columns * (height / sheet.frame_height);
+    if sheet.frames.len() > capacity {
+        return Err(format!("Sheet {} lists {} frames, but its image only holds {}", path, sheet.frames.len(), capacity));
+    }
+    
+    // Sorted so the same pixel always picks the same character
+    let mut colors = HashMap::new();
+    for (key, color) in sprite_data.color_palette.iter().chain(&sheet.palette) {
+        let rgba: [u8; 4] = match parse_color(color) {
+            Ok(color) => color.into(),
+            Err(e) => return Err(format!("Sheet {} color {}: {}", path, key, e)),
+        };
+        if let Some(ch) = key.chars().next() {
+            colors.insert(ch, rgba);
+        }
     }
+    let mut palette: Vec<(char, [u8; 4])> = colors
+        .into_iter()
+        .filter(|(_, rgba)| rgba[3] > 0)
+        .collect();
+    palette.sort_by_key(|(key, _)| *key);
+    let nearest = |pixel: &[u8]| -> char {
+        if pixel[3] < 128 {
+            return '.';
+        }
+        let distance = |rgba: &[u8; 4]| (0..3).map(|i| (rgba[i] as i32 - pixel[i] as i32).pow(2)).sum::<i32>();
+        palette.iter().min_by_key(|(_, rgba)| distance(rgba)).map_or('.', |(key, _)| *key)
+    };
     
-    // Default color names
-    match color_str {
-        "red" => RED,
-        "green" => GREEN,
-        "blue" => BLUE,
-        "yellow" => YELLOW,
-        "purple" => PURPLE,
-        "orange" => ORANGE,
-        "white" => WHITE,
-        "black" => BLACK,
-        "gray" => GRAY,
-        "darkgray" => DARKGRAY,
-        "lightgray" => LIGHTGRAY,
-        _ => WHITE,
+    let mut sprite = CharacterSprite {
+        name: sheet.name,
+        frames: HashMap::new(),
+        directions: HashMap::new(),
+        animations: sheet.animations.into_iter().collect(),
+        palette: sheet.palette.into_iter().collect(),
+        // Only set when different, so a sprite of the file's size stays plain
+        width: (sheet.frame_width != info.width).then_some(sheet.frame_width),
+        height: (sheet.frame_height != info.height).then_some(sheet.frame_height),
+        anchor: sheet.anchor,
+    };
+    for (i, frame_name) in sheet.frames.iter().enumerate() {
+        let origin_x = (i % columns) * sheet.frame_width;
+        let origin_y = (i / columns) * sheet.frame_height;
+        let rows: Vec<String> = (origin_y..origin_y + sheet.frame_height)
+            .map(|y| (origin_x..origin_x + sheet.frame_width)
+                .map(|x| nearest(&pixels[(y * width + x) * 4..][..4]))
+                .collect())
+            .collect();
+        match frame_name.split_once('/') {
+            Some((direction, frame_name)) => {
+                sprite.directions.entry(direction.to_string()).or_default().insert(frame_name.to_string(), rows);
+            },
+            None => {
+                sprite.frames.insert(frame_name.clone(), rows);
+            },
+        }
     }
+    Ok(sprite)
+}
+
+// The image as 8-bit RGBA, whatever color type it was saved with
+fn read_png(path: &Path) -> Result<(usize, usize, Vec<u8>), String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("Sheet image read error: {}: {}", path.display(), e)),
+    };
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = match decoder.read_info() {
+        Ok(reader) => reader,
+        Err(e) => return Err(format!("PNG decoding error: {}: {}", path.display(), e)),
+    };
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = match reader.next_frame(&mut buffer) {
+        Ok(info) => info,
+        Err(e) => return Err(format!("PNG decoding error: {}: {}", path.display(), e)),
+    };
+    let buffer = &buffer[..info.buffer_size()];
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer.to_vec(),
+        png::ColorType::Rgb => buffer.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        png::ColorType::Indexed => return Err(format!("PNG decoding error: {}: palette wasn't expanded", path.display())),
+    };
+    Ok((info.width as usize, info.height as usize, pixels))
 }
 
 pub fn load_sprites(path: &str) -> Result<SpriteData, String> {
     match std::fs::read_to_string(path) {
         Ok(contents) => {
             match serde_json::from_str::<SpriteData>(&contents) {
-                Ok(data) => Ok(data),
+                Ok(mut data) => {
+                    for (key, color) in &data.color_palette {
+                        if let Err(e) = parse_color(color) {
+                            return Err(format!("Sprite file {} color {}: {}", path, key, e));
+                        }
+                    }
+                    let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+                    let sheets: Vec<(String, String)> = data.sheets.iter().map(|(name, sheet)| (name.clone(), sheet.clone())).collect();
+                    for (sprite_name, sheet) in sheets {
+                        let sprite = load_sheet(&dir.join(sheet).to_string_lossy(), &data)?;
+                        data.sprites.insert(sprite_name, sprite);
+                    }
+                    for (sprite_name, sprite) in &data.sprites {
+                        let (width, height) = sprite.size(&data.sprite_info);
+                        for (frame_name, rows) in sprite.frames.iter().chain(sprite.directions.values().flatten()) {
+                            if rows.len() != height || rows.iter().any(|row| row.chars().count() != width) {
+                                return Err(format!("Sprite {} frame {} isn't {}x{}", sprite_name, frame_name, width, height));
+                            }
+                        }
+                        for (key, color) in &sprite.palette {
+                            if let Err(e) = parse_color(color) {
+                                return Err(format!("Sprite {} color {}: {}", sprite_name, key, e));
+                            }
+                        }
+                        for direction in sprite.directions.keys() {
+                            if direction != "side" && !DIRECTION_NAMES.contains(&direction.as_str()) {
+                                return Err(format!("Sprite {} has frames for unknown direction {}", sprite_name, direction));
+                            }
+                        }
+                    }
+                    Ok(data)
+                },
                 Err(e) => Err(format!("JSON parsing error: {}", e)),
             }
         },
         Err(e) => Err(format!("File read error: {}", e)),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heading_keeps_its_side_when_turning_up_or_down() {
+        let mut heading = Heading::default();
+        heading.turn(Facing::Left);
+        heading.turn(Facing::Up);
+        assert_eq!(heading.facing, Facing::Up);
+        assert!(heading.left);
+        heading.turn(Facing::Down);
+        assert!(heading.left);
+        heading.turn(Facing::UpRight);
+        assert!(!heading.left);
+        heading.turn(Facing::Down);
+        assert!(!heading.left);
+    }
+}
@@ -0,0 +1,997 @@
+use base64::Engine as _;
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use crate::{MapData, MapInfo, MapLayers, SpawnPoint, TileGrid, TileType, TilesetInfo};
+
+// Imports maps made with the Tiled editor (https://www.mapeditor.org), either
+// as XML (.tmx) or JSON (.tmj). Tile layers named ground, decoration,
+// collision and overhead become MapData layers, objects become spawns,
+// triggers, NPCs and merchants, and custom properties fill in the fields.
+
+// Chars handed out to tiles that don't pick one with a `char` property
+const TILE_CHARS: &str = ".#~,:;=+*%&@$ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_^<>()[]{}/|";
+// Tiled keeps flip and rotation flags in the top bits of a gid
+const GID_MASK: u32 = 0x0FFF_FFFF;
+const DEFAULT_TILE_COLOR: &str = "#808080";
+
+type Properties = Vec<(String, Value)>;
+
+struct TiledMap {
+    width: usize,
+    height: usize,
+    tile_size: f32,
+    properties: Properties,
+    tile_layers: Vec<TileLayer>,
+    objects: Vec<TiledObject>,
+    tilesets: Vec<TiledTileset>,
+    // Tiles with a class, properties or animation, keyed by gid
+    tiles: HashMap<u32, TiledTile>,
+}
+
+struct TiledTileset {
+    name: String,
+    firstgid: u32,
+    // Relative to the working directory; None for image collection tilesets
+    image: Option<String>,
+    tile_size: f32,
+}
+
+struct TileLayer {
+    name: String,
+    properties: Properties,
+    gids: Vec<u32>,
+}
+
+struct TiledTile {
+    class: String,
+    properties: Properties,
+    // Tile ids within the tileset and how long each shows, in milliseconds
+    animation: Vec<(usize, f32)>,
+}
+
+struct TiledObject {
+    name: String,
+    class: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    // Tile objects are anchored at their bottom-left corner
+    is_tile: bool,
+    properties: Properties,
+}
+
+pub fn is_tiled_map(path: &str) -> bool {
+    path.ends_with(".tmx") || path.ends_with(".tmj")
+}
+
+pub fn load_tiled_map(path: &str) -> Result<MapData, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("Tiled map read error: {}: {}", path, e)),
+    };
+    let dir = Path::new(path).parent().unwrap_or(Path::new(".")).to_path_buf();
+    let map = if path.ends_with(".tmx") {
+        parse_tmx(&contents, &dir)
+    } else {
+        parse_tmj(&contents, &dir)
+    };
+    let map = map.map_err(|e| format!("Tiled map {}: {}", path, e))?;
+
+    let stem = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("map");
+    convert(map, stem).map_err(|e| format!("Tiled map {}: {}", path, e))
+}
+
+// `--import-tiled <map.tmx|map.tmj> <out.yaml>` on the command line
+pub fn import_args() -> Option<(String, String)> {
+    let args: Vec<String> = std::env::args().collect();
+    let i = args.iter().position(|arg| arg == "--import-tiled")?;
+    Some((args.get(i + 1)?.clone(), args.get(i + 2)?.clone()))
+}
+
+pub fn convert_file(input: &str, output: &str) -> Result<(), String> {
+    let map = load_tiled_map(input)?;
+    let mut value = match serde_yaml::to_value(&map) {
+        Ok(value) => value,
+        Err(e) => return Err(format!("Map YAML writing error: {}", e)),
+    };
+    // Same input, same output: tile types are written in char order
+    if let Some(Value::Mapping(tile_types)) = value.get_mut("tile_types") {
+        let mut sorted: Vec<(Value, Value)> = std::mem::take(tile_types).into_iter().collect();
+        sorted.sort_by(|(a, _), (b, _)| a.as_str().cmp(&b.as_str()));
+        tile_types.extend(sorted);
+    }
+    let yaml = match serde_yaml::to_string(&value) {
+        Ok(yaml) => yaml,
+        Err(e) => return Err(format!("Map YAML writing error: {}", e)),
+    };
+    let header = format!("# Converted from {} - edit the Tiled map and convert again rather than this file\n\n", input);
+    match std::fs::write(output, header + yaml.as_str()) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Map file write error: {}: {}", output, e)),
+    }
+}
+
+fn convert(map: TiledMap, default_name: &str) -> Result<MapData, String> {
+    let tile_size = map.tile_size;
+
+    // One tile type per gid that is actually painted
+    let mut used: Vec<u32> = map.tile_layers.iter().flat_map(|layer| layer.gids.iter().copied()).filter(|gid| *gid != 0).collect();
+    used.sort();
+    used.dedup();
+
+    let mut chars: HashMap<u32, char> = HashMap::new();
+    for gid in &used {
+        if let Some(Value::String(text)) = map.tiles.get(gid).and_then(|tile| property(&tile.properties, "char")) {
+            let mut letters = text.chars();
+            let c = match (letters.next(), letters.next()) {
+                (Some(c), None) if c != ' ' => c,
+                _ => return Err(format!("tile {} has a `char` that isn't a single character", gid)),
+            };
+            if chars.values().any(|taken| *taken == c) {
+                return Err(format!("char '{}' is used by more than one tile", c));
+            }
+            chars.insert(*gid, c);
+        }
+    }
+    let picked: Vec<char> = chars.values().copied().collect();
+    let mut free = TILE_CHARS.chars().filter(|c| !picked.contains(c));
+    for gid in &used {
+        if !chars.contains_key(gid) {
+            match free.next() {
+                Some(c) => {
+                    chars.insert(*gid, c);
+                },
+                None => return Err(format!("more than {} different tiles", TILE_CHARS.len())),
+            }
+        }
+    }
+
+    let mut tile_types = HashMap::new();
+    for gid in &used {
+        let mut fields = Mapping::new();
+        fields.insert("name".into(), format!("tile_{}", gid).into());
+        fields.insert("walkable".into(), true.into());
+        fields.insert("color".into(), DEFAULT_TILE_COLOR.into());
+        if let Some(texture) = tile_texture(&map, *gid) {
+            fields.insert("texture".into(), texture);
+        }
+        if let Some(tile) = map.tiles.get(gid) {
+            if !tile.class.is_empty() {
+                fields.insert("name".into(), tile.class.clone().into());
+            }
+            for (key, value) in &tile.properties {
+                if key != "char" {
+                    fields.insert(key.clone().into(), value.clone());
+                }
+            }
+        }
+        let tile_type: TileType = match serde_yaml::from_value(Value::Mapping(fields)) {
+            Ok(tile_type) => tile_type,
+            Err(e) => return Err(format!("tile {}: {}", gid, e)),
+        };
+        tile_types.insert(chars[gid].to_string(), tile_type);
+    }
+
+    // Tile layers go by name, or by a `layer` property; a lone layer is the ground
+    let mut layers = MapLayers::default();
+    let single = map.tile_layers.len() == 1;
+    for layer in &map.tile_layers {
+        let role = match property(&layer.properties, "layer") {
+            Some(Value::String(role)) => role.to_lowercase(),
+            _ => layer.name.to_lowercase(),
+        };
+        let rows = layer_rows(&layer.gids, map.width, &chars);
+        let target = match role.as_str() {
+            "ground" => &mut layers.ground,
+            "decoration" => &mut layers.decoration,
+            "collision" => &mut layers.collision,
+            "overhead" => &mut layers.overhead,
+            _ if single => &mut layers.ground,
+            _ => return Err(format!(
+                "tile layer '{}' should be named ground, decoration, collision or overhead",
+                layer.name
+            )),
+        };
+        if !target.is_empty() {
+            return Err(format!("more than one {} layer", role));
+        }
+        *target = rows;
+    }
+    if layers.ground.is_empty() {
+        return Err("no ground tile layer".to_string());
+    }
+
+    let name = match property(&map.properties, "name") {
+        Some(Value::String(name)) => name.clone(),
+        _ => default_name.to_string(),
+    };
+    let mut spawn_point = SpawnPoint {
+        x: (map.width / 2) as f32,
+        y: (map.height / 2) as f32,
+    };
+    let mut monster_spawns = Vec::new();
+    let mut merchants = Vec::new();
+    let mut npcs = Vec::new();
+    let mut triggers = Vec::new();
+
+    for object in &map.objects {
+        // Points and NPCs stand where the object's center is
+        let (center_x, center_y) = if object.is_tile {
+            (object.x + object.width / 2.0, object.y - object.height / 2.0)
+        } else {
+            (object.x + object.width / 2.0, object.y + object.height / 2.0)
+        };
+        let tile_x = (center_x / tile_size).floor();
+        let tile_y = (center_y / tile_size).floor();
+
+        let mut fields = Mapping::new();
+        fields.insert("name".into(), object.name.clone().into());
+        for (key, value) in &object.properties {
+            fields.insert(key.clone().into(), value.clone());
+        }
+
+        match object.class.as_str() {
+            "spawn_point" => {
+                spawn_point = SpawnPoint { x: tile_x, y: tile_y };
+            },
+            "monster" | "spawn" => {
+                fields.remove("name");
+                if !fields.contains_key("monster_type") {
+                    fields.insert("monster_type".into(), object.name.clone().into());
+                }
+                fields.insert("x".into(), tile_x.into());
+                fields.insert("y".into(), tile_y.into());
+                monster_spawns.push(object_from(fields, object)?);
+            },
+            "npc" => {
+                fields.insert("x".into(), tile_x.into());
+                fields.insert("y".into(), tile_y.into());
+                npcs.push(object_from(fields, object)?);
+            },
+            "merchant" => {
+                fields.insert("x".into(), tile_x.into());
+                fields.insert("y".into(), tile_y.into());
+                merchants.push(object_from(fields, object)?);
+            },
+            "trigger" => {
+                // Rectangles cover the tiles they touch; points cover one tile
+                let x = (object.x / tile_size).floor();
+                let y = (object.y / tile_size).floor();
+                let width = ((object.x + object.width) / tile_size).ceil() - x;
+                let height = ((object.y + object.height) / tile_size).ceil() - y;
+                fields.insert("x".into(), x.into());
+                fields.insert("y".into(), y.into());
+                fields.insert("width".into(), width.max(1.0).into());
+                fields.insert("height".into(), height.max(1.0).into());
+                triggers.push(object_from(fields, object)?);
+            },
+            other => println!("Ignoring Tiled object '{}' of unknown type '{}'", object.name, other),
+        }
+    }
+
+    let tilesets = map.tilesets.iter()
+        .filter_map(|tileset| tileset.image.as_ref().map(|image| (tileset.name.clone(), TilesetInfo {
+            image: image.clone(),
+            tile_size: tileset.tile_size,
+        })))
+        .collect();
+
+    Ok(MapData {
+        map_info: MapInfo {
+            name,
+            width: map.width,
+            height: map.height,
+            tile_size,
+            spawn_point,
+        },
+        tile_types,
+        tilesets,
+        layout: Vec::new(),
+        layers,
+        monster_spawns: Some(monster_spawns).filter(|list| !list.is_empty()),
+        merchants: Some(merchants).filter(|list| !list.is_empty()),
+        npcs: Some(npcs).filter(|list| !list.is_empty()),
+        triggers: Some(triggers).filter(|list| !list.is_empty()),
+        grid: TileGrid::default(),
+    })
+}
+
+// The picture of a tile from an image tileset, with its animation if any
+fn tile_texture(map: &TiledMap, gid: u32) -> Option<Value> {
+    let tileset = map.tilesets.iter().filter(|tileset| tileset.firstgid <= gid).max_by_key(|tileset| tileset.firstgid)?;
+    tileset.image.as_ref()?;
+
+    let mut texture = Mapping::new();
+    texture.insert("tileset".into(), tileset.name.clone().into());
+    texture.insert("tile".into(), ((gid - tileset.firstgid) as u64).into());
+    if let Some(tile) = map.tiles.get(&gid).filter(|tile| !tile.animation.is_empty()) {
+        let frames: Vec<Value> = tile.animation.iter().map(|(id, _)| (*id as u64).into()).collect();
+        // Frames share one duration here, so use the average
+        let total: f32 = tile.animation.iter().map(|(_, duration)| duration).sum();
+        texture.insert("frames".into(), Value::Sequence(frames));
+        texture.insert("frame_time".into(), ((total / tile.animation.len() as f32 / 1000.0) as f64).into());
+    }
+    Some(Value::Mapping(texture))
+}
+
+fn object_from<T: serde::de::DeserializeOwned>(fields: Mapping, object: &TiledObject) -> Result<T, String> {
+    match serde_yaml::from_value(Value::Mapping(fields)) {
+        Ok(value) => Ok(value),
+        Err(e) => Err(format!("{} object '{}': {}", object.class, object.name, e)),
+    }
+}
+
+fn layer_rows(gids: &[u32], width: usize, chars: &HashMap<u32, char>) -> Vec<String> {
+    gids.chunks(width.max(1))
+        .map(|row| row.iter().map(|gid| chars.get(gid).copied().unwrap_or(' ')).collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+fn property<'a>(properties: &'a Properties, name: &str) -> Option<&'a Value> {
+    properties.iter().find(|(key, _)| key == name).map(|(_, value)| value)
+}
+
+// Strings that look like YAML lists, maps or tagged values (`[a, b]`,
+// `- !set_flag x`) are parsed, so triggers can carry their actions.
+// Tiled writes colors as #AARRGGBB; the alpha is dropped.
+fn property_value(kind: &str, text: &str) -> Value {
+    match kind {
+        "bool" => Value::Bool(text == "true"),
+        "int" => text.parse::<i64>().map(Value::from).unwrap_or_else(|_| text.into()),
+        "float" => text.parse::<f64>().map(Value::from).unwrap_or_else(|_| text.into()),
+        "color" if text.len() == 9 && text.starts_with('#') => format!("#{}", &text[3..]).into(),
+        _ => {
+            let trimmed = text.trim_start();
+            let structured = trimmed.starts_with('[') || trimmed.starts_with('{') || trimmed.starts_with("- ") || trimmed.starts_with('!');
+            if structured {
+                if let Ok(value) = serde_yaml::from_str::<Value>(text) {
+                    return value;
+                }
+            }
+            text.into()
+        },
+    }
+}
+
+fn decode_gids(text: &str, encoding: &str, compression: &str) -> Result<Vec<u32>, String> {
+    match encoding {
+        "csv" => text.split(',')
+            .map(|gid| gid.trim())
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| gid.parse::<u32>().map(|gid| gid & GID_MASK).map_err(|e| format!("bad tile id '{}': {}", gid, e)))
+            .collect(),
+        "base64" => {
+            let raw = match base64::engine::general_purpose::STANDARD.decode(text.trim()) {
+                Ok(raw) => raw,
+                Err(e) => return Err(format!("bad base64 tile data: {}", e)),
+            };
+            let mut bytes = Vec::new();
+            let result = match compression {
+                "" => {
+                    bytes = raw;
+                    Ok(0)
+                },
+                "zlib" => flate2::read::ZlibDecoder::new(raw.as_slice()).read_to_end(&mut bytes),
+                "gzip" => flate2::read::GzDecoder::new(raw.as_slice()).read_to_end(&mut bytes),
+                other => return Err(format!("unsupported tile layer compression '{}'", other)),
+            };
+            if let Err(e) = result {
+                return Err(format!("bad {} tile data: {}", compression, e));
+            }
+            Ok(bytes.chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) & GID_MASK)
+                .collect())
+        },
+        other => Err(format!("unsupported tile layer encoding '{}'", other)),
+    }
+}
+
+// Paths in Tiled files are relative to the file they appear in
+fn resolve(dir: &Path, source: &str) -> PathBuf {
+    let mut path = PathBuf::new();
+    for part in dir.join(source).components() {
+        match part {
+            Component::ParentDir if path.file_name().is_some() => {
+                path.pop();
+            },
+            Component::CurDir => {},
+            other => path.push(other),
+        }
+    }
+    path
+}
+
+// Returns the tileset's contents and the directory its own paths start from
+fn read_tileset(dir: &Path, source: &str) -> Result<(String, PathBuf), String> {
+    let path = resolve(dir, source);
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Ok((contents, path.parent().unwrap_or(dir).to_path_buf())),
+        Err(e) => Err(format!("tileset read error: {}: {}", path.display(), e)),
+    }
+}
+
+// --- JSON (.tmj / .tsj) ---
+
+#[derive(Deserialize)]
+struct TmjMap {
+    width: usize,
+    height: usize,
+    tilewidth: f32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    properties: Vec<TmjProperty>,
+    layers: Vec<TmjLayer>,
+    #[serde(default)]
+    tilesets: Vec<TmjTileset>,
+}
+
+#[derive(Deserialize, Clone)]
+struct TmjProperty {
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    value: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct TmjLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    properties: Vec<TmjProperty>,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    compression: Option<String>,
+    #[serde(default)]
+    objects: Vec<TmjObject>,
+    // Children of a group layer
+    #[serde(default)]
+    layers: Vec<TmjLayer>,
+}
+
+#[derive(Deserialize)]
+struct TmjObject {
+    #[serde(default)]
+    name: String,
+    // Called `class` in Tiled 1.9 and `type` before and after it
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<TmjProperty>,
+}
+
+#[derive(Deserialize, Clone)]
+struct TmjTileset {
+    #[serde(default)]
+    firstgid: u32,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    tilewidth: f32,
+    #[serde(default)]
+    tiles: Vec<TmjTile>,
+}
+
+#[derive(Deserialize, Clone)]
+struct TmjTile {
+    id: u32,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    properties: Vec<TmjProperty>,
+    #[serde(default)]
+    animation: Vec<TmjFrame>,
+}
+
+#[derive(Deserialize, Clone)]
+struct TmjFrame {
+    tileid: usize,
+    duration: f32,
+}
+
+fn tmj_properties(properties: &[TmjProperty]) -> Properties {
+    properties.iter().map(|property| {
+        let value = match &property.value {
+            serde_json::Value::String(text) => property_value(&property.kind, text),
+            other => serde_yaml::to_value(other).unwrap_or(Value::Null),
+        };
+        (property.name.clone(), value)
+    }).collect()
+}
+
+fn parse_tmj(contents: &str, dir: &Path) -> Result<TiledMap, String> {
+    let tmj = match serde_json::from_str::<TmjMap>(contents) {
+        Ok(tmj) => tmj,
+        Err(e) => return Err(format!("JSON parsing error: {}", e)),
+    };
+    if tmj.infinite {
+        return Err("infinite maps aren't supported".to_string());
+    }
+
+    let mut map = TiledMap {
+        width: tmj.width,
+        height: tmj.height,
+        tile_size: tmj.tilewidth,
+        properties: tmj_properties(&tmj.properties),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+        tilesets: Vec::new(),
+        tiles: HashMap::new(),
+    };
+
+    for tileset in &tmj.tilesets {
+        match &tileset.source {
+            Some(source) if source.ends_with(".tsx") => {
+                let (contents, tileset_dir) = read_tileset(dir, source)?;
+                add_tsx_tileset(&mut map, &contents, tileset.firstgid, &tileset_dir)?;
+            },
+            Some(source) => {
+                let (contents, tileset_dir) = read_tileset(dir, source)?;
+                match serde_json::from_str::<TmjTileset>(&contents) {
+                    Ok(external) => add_tmj_tileset(&mut map, &external, tileset.firstgid, &tileset_dir),
+                    Err(e) => return Err(format!("tileset {}: JSON parsing error: {}", source, e)),
+                }
+            },
+            None => add_tmj_tileset(&mut map, tileset, tileset.firstgid, dir),
+        }
+    }
+
+    add_tmj_layers(&mut map, &tmj.layers)?;
+    Ok(map)
+}
+
+fn add_tmj_tileset(map: &mut TiledMap, tileset: &TmjTileset, firstgid: u32, dir: &Path) {
+    map.tilesets.push(TiledTileset {
+        name: tileset_name(&tileset.name, firstgid),
+        firstgid,
+        image: tileset.image.as_ref().map(|image| resolve(dir, image).to_string_lossy().into_owned()),
+        tile_size: tileset.tilewidth,
+    });
+    for tile in &tileset.tiles {
+        let class = if tile.class.is_empty() { tile.kind.clone() } else { tile.class.clone() };
+        map.tiles.insert(firstgid + tile.id, TiledTile {
+            class,
+            properties: tmj_properties(&tile.properties),
+            animation: tile.animation.iter().map(|frame| (frame.tileid, frame.duration)).collect(),
+        });
+    }
+}
+
+fn tileset_name(name: &str, firstgid: u32) -> String {
+    if name.is_empty() { format!("tileset_{}", firstgid) } else { name.to_string() }
+}
+
+fn add_tmj_layers(map: &mut TiledMap, layers: &[TmjLayer]) -> Result<(), String> {
+    for layer in layers {
+        match layer.kind.as_str() {
+            "tilelayer" => {
+                let gids = match &layer.data {
+                    Some(serde_json::Value::String(text)) => decode_gids(
+                        text,
+                        layer.encoding.as_deref().unwrap_or("base64"),
+                        layer.compression.as_deref().unwrap_or(""),
+                    )?,
+                    Some(serde_json::Value::Array(values)) => values.iter()
+                        .map(|value| value.as_u64().unwrap_or(0) as u32 & GID_MASK)
+                        .collect(),
+                    _ => return Err(format!("tile layer '{}' has no data", layer.name)),
+                };
+                map.tile_layers.push(TileLayer {
+                    name: layer.name.clone(),
+                    properties: tmj_properties(&layer.properties),
+                    gids,
+                });
+            },
+            "objectgroup" => {
+                for object in &layer.objects {
+                    map.objects.push(TiledObject {
+                        name: object.name.clone(),
+                        class: if object.class.is_empty() { object.kind.clone() } else { object.class.clone() },
+                        x: object.x,
+                        y: object.y,
+                        width: object.width,
+                        height: object.height,
+                        is_tile: object.gid.is_some(),
+                        properties: tmj_properties(&object.properties),
+                    });
+                }
+            },
+            "group" => add_tmj_layers(map, &layer.layers)?,
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+// --- XML (.tmx / .tsx) ---
+
+fn attr<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Option<T> {
+    node.attribute(name).and_then(|value| value.parse().ok())
+}
+
+fn tmx_properties(node: roxmltree::Node) -> Properties {
+    let properties = match node.children().find(|child| child.has_tag_name("properties")) {
+        Some(properties) => properties,
+        None => return Vec::new(),
+    };
+    properties.children()
+        .filter(|child| child.has_tag_name("property"))
+        .map(|property| {
+            let name = property.attribute("name").unwrap_or("").to_string();
+            let kind = property.attribute("type").unwrap_or("string");
+            // Multi-line strings are stored as the element's text
+            let text = property.attribute("value").or_else(|| property.text()).unwrap_or("");
+            (name, property_value(kind, text))
+        })
+        .collect()
+}
+
+fn tmx_class(node: roxmltree::Node) -> String {
+    node.attribute("class").or_else(|| node.attribute("type")).unwrap_or("").to_string()
+}
+
+fn add_tsx_tileset(map: &mut TiledMap, contents: &str, firstgid: u32, dir: &Path) -> Result<(), String> {
+    let document = match roxmltree::Document::parse(contents) {
+        Ok(document) => document,
+        Err(e) => return Err(format!("tileset XML parsing error: {}", e)),
+    };
+    add_tmx_tileset(map, document.root_element(), firstgid, dir);
+    Ok(())
+}
+
+fn add_tmx_tileset(map: &mut TiledMap, tileset: roxmltree::Node, firstgid: u32, dir: &Path) {
+    let image = tileset.children()
+        .find(|child| child.has_tag_name("image"))
+        .and_then(|image| image.attribute("source"))
+        .map(|source| resolve(dir, source).to_string_lossy().into_owned());
+    map.tilesets.push(TiledTileset {
+        name: tileset_name(tileset.attribute("name").unwrap_or(""), firstgid),
+        firstgid,
+        image,
+        tile_size: attr(tileset, "tilewidth").unwrap_or(map.tile_size),
+    });
+
+    for tile in tileset.children().filter(|child| child.has_tag_name("tile")) {
+        let id: u32 = attr(tile, "id").unwrap_or(0);
+        let animation = tile.children()
+            .find(|child| child.has_tag_name("animation"))
+            .map(|animation| animation.children()
+                .filter(|frame| frame.has_tag_name("frame"))
+                .map(|frame| (attr(frame, "tileid").unwrap_or(0), attr(frame, "duration").unwrap_or(100.0)))
+                .collect())
+            .unwrap_or_default();
+        map.tiles.insert(firstgid + id, TiledTile {
+            class: tmx_class(tile),
+            properties: tmx_properties(tile),
+            animation,
+        });
+    }
+}
+
+fn parse_tmx(contents: &str, dir: &Path) -> Result<TiledMap, String> {
+    let document = match roxmltree::Document::parse(contents) {
+        Ok(document) => document,
+        Err(e) => return Err(format!("XML parsing error: {}", e)),
+    };
+    let root = document.root_element();
+    if attr::<u32>(root, "infinite") == Some(1) {
+        return Err("infinite maps aren't supported".to_string());
+    }
+
+    let mut map = TiledMap {
+        width: attr(root, "width").ok_or("map has no width")?,
+        height: attr(root, "height").ok_or("map has no height")?,
+        tile_size: attr(root, "tilewidth").ok_or("map has no tilewidth")?,
+        properties: tmx_properties(root),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+        tilesets: Vec::new(),
+        tiles: HashMap::new(),
+    };
+
+    for tileset in root.children().filter(|child| child.has_tag_name("tileset")) {
+        let firstgid = attr(tileset, "firstgid").unwrap_or(1);
+        match tileset.attribute("source") {
+            Some(source) => {
+                let (contents, tileset_dir) = read_tileset(dir, source)?;
+                add_tsx_tileset(&mut map, &contents, firstgid, &tileset_dir)?;
+            },
+            None => add_tmx_tileset(&mut map, tileset, firstgid, dir),
+        }
+    }
+
+    add_tmx_layers(&mut map, root)?;
+    Ok(map)
+}
+
+fn add_tmx_layers(map: &mut TiledMap, parent: roxmltree::Node) -> Result<(), String> {
+    for node in parent.children().filter(|child| child.is_element()) {
+        match node.tag_name().name() {
+            "layer" => {
+                let name = node.attribute("name").unwrap_or("").to_string();
+                let data = match node.children().find(|child| child.has_tag_name("data")) {
+                    Some(data) => data,
+                    None => return Err(format!("tile layer '{}' has no data", name)),
+                };
+                let gids = match data.attribute("encoding") {
+                    Some(encoding) => decode_gids(data.text().unwrap_or(""), encoding, data.attribute("compression").unwrap_or(""))?,
+                    // Unencoded layers list one <tile gid=".."/> per cell
+                    None => data.children()
+                        .filter(|child| child.has_tag_name("tile"))
+                        .map(|tile| attr::<u32>(tile, "gid").unwrap_or(0) & GID_MASK)
+                        .collect(),
+                };
+                map.tile_layers.push(TileLayer {
+                    name,
+                    properties: tmx_properties(node),
+                    gids,
+                });
+            },
+            "objectgroup" => {
+                for object in node.children().filter(|child| child.has_tag_name("object")) {
+                    map.objects.push(TiledObject {
+                        name: object.attribute("name").unwrap_or("").to_string(),
+                        class: tmx_class(object),
+                        x: attr(object, "x").unwrap_or(0.0),
+                        y: attr(object, "y").unwrap_or(0.0),
+                        width: attr(object, "width").unwrap_or(0.0),
+                        height: attr(object, "height").unwrap_or(0.0),
+                        is_tile: object.attribute("gid").is_some(),
+                        properties: tmx_properties(object),
+                    });
+                }
+            },
+            "group" => add_tmx_layers(map, node)?,
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trigger_system::{TriggerAction, TriggerKind};
+    use std::io::Write;
+
+    const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps");
+    // Tiled's horizontal and diagonal flip flags
+    const FLIP_X: u32 = 0x8000_0000;
+    const FLIP_DIAGONAL: u32 = 0x2000_0000;
+
+    fn little_endian(gids: &[u32]) -> Vec<u8> {
+        gids.iter().flat_map(|gid| gid.to_le_bytes()).collect()
+    }
+
+    fn zlib_base64(gids: &[u32]) -> String {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&little_endian(gids)).unwrap();
+        base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap())
+    }
+
+    fn gzip_base64(gids: &[u32]) -> String {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&little_endian(gids)).unwrap();
+        base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap())
+    }
+
+    // 4x3 tiles of 16px: rock around floor, with water that has no `char`
+    const GROUND: [u32; 12] = [
+        2, 2, 2, 2 | FLIP_X,
+        2, 1, 1, 2,
+        2, 1, 3 | FLIP_DIAGONAL, 2,
+    ];
+
+    fn tmx(ground_data: &str) -> String {
+        format!(r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="4" height="3" tilewidth="16" tileheight="16" infinite="0">
+ <properties>
+  <property name="name" value="Test Cave"/>
+ </properties>
+ <tileset firstgid="1" name="cave" tilewidth="16" tileheight="16">
+  <image source="cave.png" width="64" height="16"/>
+  <tile id="0" type="floor">
+   <properties>
+    <property name="char" value="."/>
+    <property name="walkable" type="bool" value="true"/>
+    <property name="color" value="tan"/>
+   </properties>
+  </tile>
+  <tile id="1" type="rock">
+   <properties>
+    <property name="char" value="#"/>
+    <property name="walkable" type="bool" value="false"/>
+    <property name="color" type="color" value="#ff112233"/>
+   </properties>
+  </tile>
+  <tile id="2" type="water">
+   <properties>
+    <property name="walkable" type="bool" value="false"/>
+    <property name="color" value="blue"/>
+   </properties>
+   <animation>
+    <frame tileid="2" duration="200"/>
+    <frame tileid="3" duration="400"/>
+   </animation>
+  </tile>
+ </tileset>
+ <layer id="1" name="ground" width="4" height="3">
+  <data {}</data>
+ </layer>
+ <layer id="2" name="decoration" width="4" height="3">
+  <data encoding="csv">
+0,0,0,0,
+0,{},0,0,
+0,0,0,0
+</data>
+ </layer>
+ <objectgroup id="3" name="objects">
+  <object id="1" name="start" type="spawn_point" x="40" y="8">
+   <point/>
+  </object>
+  <object id="2" name="Slime" type="monster" gid="1" x="16" y="48" width="16" height="16"/>
+  <object id="3" name="pond" type="trigger" x="20" y="4" width="16" height="10">
+   <properties>
+    <property name="on" value="enter"/>
+    <property name="actions">- !show_text Cold water</property>
+   </properties>
+  </object>
+ </objectgroup>
+</map>
+"##, ground_data, 3 | FLIP_X)
+    }
+
+    // What every variant of the test map converts to
+    fn assert_test_cave(map: &MapData) {
+        assert_eq!(map.map_info.name, "Test Cave");
+        assert_eq!((map.map_info.width, map.map_info.height, map.map_info.tile_size), (4, 3, 16.0));
+        // Flip bits are masked off; the tile without a `char` gets the next free one
+        assert_eq!(map.layers.ground, vec!["####", "#..#", "#.~#"]);
+        assert_eq!(map.layers.decoration, vec!["", " ~", ""]);
+
+        let rock = &map.tile_types["#"];
+        assert_eq!((rock.name.as_str(), rock.walkable, rock.color.as_str()), ("rock", false, "#112233"));
+        let floor = &map.tile_types["."];
+        assert_eq!((floor.name.as_str(), floor.walkable, floor.color.as_str()), ("floor", true, "tan"));
+        let water = map.tile_types["~"].texture.as_ref().unwrap();
+        assert_eq!((water.tileset.as_str(), water.tile), ("cave", 2));
+        assert_eq!(water.frames, vec![2, 3]);
+        assert!((water.frame_time - 0.3).abs() < 1e-6);
+
+        assert_eq!((map.map_info.spawn_point.x, map.map_info.spawn_point.y), (2.0, 0.0));
+        // A tile object's position is its bottom-left corner
+        let spawns = map.monster_spawns.as_ref().unwrap();
+        assert_eq!(spawns.len(), 1);
+        assert_eq!((spawns[0].monster_type.as_str(), spawns[0].x, spawns[0].y), ("Slime", 1.0, 2.0));
+        // Pixels 20..36 x 4..14 touch tiles 1..2 of row 0
+        let triggers = map.triggers.as_ref().unwrap();
+        assert_eq!(triggers.len(), 1);
+        let pond = &triggers[0];
+        assert_eq!((pond.x, pond.y, pond.width, pond.height), (1.0, 0.0, 2.0, 1.0));
+        assert_eq!(pond.on, TriggerKind::Enter);
+        assert!(matches!(pond.actions.as_slice(), [TriggerAction::ShowText(text)] if text == "Cold water"));
+    }
+
+    #[test]
+    fn converts_tmx_with_csv_and_zlib_layers() {
+        let csv: Vec<String> = GROUND.iter().map(|gid| gid.to_string()).collect();
+        for data in [
+            format!(r#"encoding="csv">{}"#, csv.join(",")),
+            format!(r#"encoding="base64" compression="zlib">{}"#, zlib_base64(&GROUND)),
+        ] {
+            let map = parse_tmx(&tmx(&data), Path::new("maps")).unwrap();
+            let map = convert(map, "cave").unwrap();
+            assert_test_cave(&map);
+            assert_eq!(map.tilesets["cave"].image, "maps/cave.png");
+        }
+    }
+
+    #[test]
+    fn converts_tmj_with_external_tileset_and_gzip_layer() {
+        let dir = std::env::temp_dir().join(format!("tiled_test_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("tilesets")).unwrap();
+        let tileset = r##"{
+            "name": "cave", "tilewidth": 16, "tileheight": 16, "image": "cave.png",
+            "tiles": [
+                {"id": 0, "type": "floor", "properties": [
+                    {"name": "char", "type": "string", "value": "."},
+                    {"name": "walkable", "type": "bool", "value": true},
+                    {"name": "color", "type": "string", "value": "tan"}]},
+                {"id": 1, "class": "rock", "properties": [
+                    {"name": "char", "type": "string", "value": "#"},
+                    {"name": "walkable", "type": "bool", "value": false},
+                    {"name": "color", "type": "color", "value": "#ff112233"}]},
+                {"id": 2, "type": "water", "properties": [
+                    {"name": "walkable", "type": "bool", "value": false},
+                    {"name": "color", "type": "string", "value": "blue"}],
+                 "animation": [{"tileid": 2, "duration": 200}, {"tileid": 3, "duration": 400}]}
+            ]
+        }"##;
+        std::fs::write(dir.join("tilesets/cave.tsj"), tileset).unwrap();
+        let map = format!(r##"{{
+            "width": 4, "height": 3, "tilewidth": 16, "tileheight": 16, "infinite": false,
+            "properties": [{{"name": "name", "type": "string", "value": "Test Cave"}}],
+            "tilesets": [{{"firstgid": 1, "source": "tilesets/cave.tsj"}}],
+            "layers": [
+                {{"type": "tilelayer", "name": "ground", "encoding": "base64", "compression": "gzip", "data": "{}"}},
+                {{"type": "group", "name": "details", "layers": [
+                    {{"type": "tilelayer", "name": "decoration", "data": [0, 0, 0, 0, 0, {}, 0, 0, 0, 0, 0, 0]}}
+                ]}},
+                {{"type": "objectgroup", "name": "objects", "objects": [
+                    {{"name": "start", "type": "spawn_point", "x": 40, "y": 8, "point": true}},
+                    {{"name": "Slime", "class": "monster", "gid": 1, "x": 16, "y": 48, "width": 16, "height": 16}},
+                    {{"name": "pond", "type": "trigger", "x": 20, "y": 4, "width": 16, "height": 10, "properties": [
+                        {{"name": "on", "type": "string", "value": "enter"}},
+                        {{"name": "actions", "type": "string", "value": "- !show_text Cold water"}}]}}
+                ]}}
+            ]
+        }}"##, gzip_base64(&GROUND), 3 | FLIP_X);
+        let path = dir.join("cave.tmj");
+        std::fs::write(&path, map).unwrap();
+
+        let result = load_tiled_map(&path.to_string_lossy());
+        std::fs::remove_dir_all(&dir).unwrap();
+        let map = result.unwrap();
+        assert_test_cave(&map);
+        // Tileset paths start from the tileset file
+        assert_eq!(map.tilesets["cave"].image, dir.join("tilesets/cave.png").to_string_lossy());
+    }
+
+    // forest_2_2 was a YAML map before it moved to Tiled; the old file is kept
+    // as a fixture to show the conversion loses nothing
+    #[test]
+    fn forest_tmx_matches_the_yaml_it_replaced() {
+        let tiled = load_tiled_map("data/maps/tiled/forest_2_2.tmx").unwrap();
+        let yaml = crate::load_map(&format!("{}/forest_2_2.yaml", FIXTURE_DIR)).unwrap();
+
+        assert_eq!(tiled.map_info.name, yaml.map_info.name);
+        assert_eq!((tiled.map_info.width, tiled.map_info.height), (yaml.map_info.width, yaml.map_info.height));
+        assert_eq!((tiled.map_info.spawn_point.x, tiled.map_info.spawn_point.y), (yaml.map_info.spawn_point.x, yaml.map_info.spawn_point.y));
+
+        // The Tiled map draws trees, bushes and stones on a decoration layer
+        // over the ground; stacked, they must give the old single layout
+        for (y, row) in yaml.layers.ground.iter().enumerate() {
+            for (x, old) in row.chars().enumerate() {
+                let decoration = tiled.layers.decoration.get(y).and_then(|row| row.chars().nth(x)).filter(|c| *c != ' ');
+                let new = decoration.or_else(|| tiled.layers.ground[y].chars().nth(x)).unwrap();
+                let (old_tile, new_tile) = (&yaml.tile_types[&old.to_string()], &tiled.tile_types[&new.to_string()]);
+                assert_eq!(
+                    (&old_tile.name, old_tile.walkable, &old_tile.color),
+                    (&new_tile.name, new_tile.walkable, &new_tile.color),
+                    "tile {},{}", x, y,
+                );
+            }
+        }
+
+        let spawns = |map: &MapData| -> Vec<(String, f32, f32)> {
+            map.monster_spawns.iter().flatten().map(|spawn| (spawn.monster_type.clone(), spawn.x, spawn.y)).collect()
+        };
+        assert_eq!(spawns(&tiled), spawns(&yaml));
+    }
+}
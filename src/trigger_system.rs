@@ -0,0 +1,216 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use crate::chunk_system::ChunkManager;
+
+// An event region as placed in map YAML; x/y/width/height are in tiles
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TriggerData {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default = "default_trigger_size")]
+    pub width: f32,
+    #[serde(default = "default_trigger_size")]
+    pub height: f32,
+    pub on: TriggerKind,
+    #[serde(default)]
+    pub conditions: Vec<TriggerCondition>,
+    pub actions: Vec<TriggerAction>,
+}
+
+fn default_trigger_size() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerKind {
+    Enter,
+    Exit,
+    Interact,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerCondition {
+    FlagSet(String),
+    FlagNotSet(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerAction {
+    ShowText(String),
+    // Tile coordinates; without a chunk the warp stays in the trigger's own chunk
+    Warp {
+        #[serde(default)]
+        chunk: Option<String>,
+        x: f32,
+        y: f32,
+    },
+    SpawnMonster {
+        monster: String,
+        x: f32,
+        y: f32,
+    },
+    SetFlag(String),
+    ClearFlag(String),
+    // Path of a Rhai script whose top-level statements are run
+    Script(String),
+}
+
+impl TriggerCondition {
+    pub fn is_met(&self, flags: &HashSet<String>) -> bool {
+        match self {
+            TriggerCondition::FlagSet(flag) => flags.contains(flag),
+            TriggerCondition::FlagNotSet(flag) => !flags.contains(flag),
+        }
+    }
+}
+
+// A trigger that fired this frame, with the chunk it belongs to
+pub struct FiredTrigger {
+    pub chunk_id: String,
+    pub actions: Vec<TriggerAction>,
+}
+
+pub struct TriggerTracker {
+    // (chunk id, trigger index) of every region the player is standing in
+    inside: HashSet<(String, usize)>,
+    message: Option<(String, f32)>,
+}
+
+impl TriggerTracker {
+    pub fn new() -> Self {
+        TriggerTracker {
+            inside: HashSet::new(),
+            message: None,
+        }
+    }
+
+    // Checks every trigger in the loaded chunks against the player position.
+    // Enter/exit fire on the frame the player crosses the edge, interact on E.
+    pub fn update(
+        &mut self,
+        chunk_manager: &ChunkManager,
+        player_pos: Vec2,
+        interact_pressed: bool,
+        flags: &HashSet<String>,
+    ) -> Vec<FiredTrigger> {
+        let mut fired = Vec::new();
+        let mut now_inside = HashSet::new();
+
+        for chunk_id in &chunk_manager.loaded_chunks {
+            let chunk = match chunk_manager.chunks.get(chunk_id) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let tile_size = chunk.map_data.map_info.tile_size;
+
+            for (i, trigger) in chunk.map_data.triggers.iter().flatten().enumerate() {
+                let corner = chunk_manager.chunk_tile_to_world(chunk, trigger.x, trigger.y);
+                let area = Rect::new(corner.x, corner.y, trigger.width * tile_size, trigger.height * tile_size);
+                let key = (chunk_id.clone(), i);
+                let is_inside = area.contains(player_pos);
+                let was_inside = self.inside.contains(&key);
+
+                let fires = match trigger.on {
+                    TriggerKind::Enter => is_inside && !was_inside,
+                    TriggerKind::Exit => !is_inside && was_inside,
+                    TriggerKind::Interact => is_inside && interact_pressed,
+                };
+                if fires && trigger.conditions.iter().all(|condition| condition.is_met(flags)) {
+                    fired.push(FiredTrigger {
+                        chunk_id: chunk_id.clone(),
+                        actions: trigger.actions.clone(),
+                    });
+                }
+
+                if is_inside {
+                    now_inside.insert(key);
+                }
+            }
+        }
+
+        self.inside = now_inside;
+        fired
+    }
+
+    pub fn show_text(&mut self, text: &str) {
+        self.message = Some((text.to_string(), 4.0));
+    }
+
+    pub fn draw(&mut self, delta: f32) {
+        let (text, timer) = match &mut self.message {
+            Some(message) => message,
+            None => return,
+        };
+
+        let width = measure_text(text, None, 22, 1.0).width;
+        let box_width = width + 40.0;
+        let box_x = screen_width() / 2.0 - box_width / 2.0;
+        draw_rectangle(box_x, 70.0, box_width, 40.0, Color::new(0.0, 0.0, 0.0, 0.8));
+        draw_rectangle_lines(box_x, 70.0, box_width, 40.0, 2.0, WHITE);
+        draw_text(text, box_x + 20.0, 97.0, 22.0, WHITE);
+
+        *timer -= delta;
+        if *timer <= 0.0 {
+            self.message = None;
+        }
+    }
+}
+
+pub struct WarpTarget {
+    pub chunk: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+const FADE_TIME: f32 = 0.3; // seconds to fade out, and again to fade back in
+
+// Fades to black, hands out the warp at the darkest point, then fades back in
+pub struct WarpTransition {
+    target: Option<WarpTarget>,
+    timer: Option<f32>,
+}
+
+impl WarpTransition {
+    pub fn new() -> Self {
+        WarpTransition {
+            target: None,
+            timer: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    pub fn start(&mut self, target: WarpTarget) {
+        if !self.is_active() {
+            self.target = Some(target);
+            self.timer = Some(0.0);
+        }
+    }
+
+    pub fn update(&mut self, delta: f32) -> Option<WarpTarget> {
+        let timer = self.timer.as_mut()?;
+        *timer += delta;
+
+        if *timer >= FADE_TIME * 2.0 {
+            self.timer = None;
+        }
+        if self.timer.is_none_or(|timer| timer >= FADE_TIME) {
+            return self.target.take();
+        }
+        None
+    }
+
+    pub fn draw(&self) {
+        if let Some(timer) = self.timer {
+            let alpha = if timer < FADE_TIME { timer / FADE_TIME } else { 2.0 - timer / FADE_TIME };
+            draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.0, 0.0, 0.0, alpha.clamp(0.0, 1.0)));
+        }
+    }
+}